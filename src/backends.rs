@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use console::style;
-use reqwest::{ClientBuilder, RequestBuilder, Url};
+use log::{debug, trace};
+use reqwest::{ClientBuilder, RequestBuilder, Response, Url};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
@@ -12,8 +13,8 @@ use tokio::time;
 
 use reqwest::{Client, header};
 
-use crate::cli::clone::RemoteRepo;
 use crate::common::get_araki_cache;
+use crate::remote::RemoteRepo;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GitHubCreateRepositoryRequestBody {
@@ -21,8 +22,34 @@ struct GitHubCreateRepositoryRequestBody {
     private: bool,
 }
 
+/// A user of a backend.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackendUser {
+    /// Username used to log in
+    pub login: String,
+    /// Display name, if the user has set one
+    pub name: Option<String>,
+    /// Public email address, if the user has set one
+    pub email: Option<String>,
+}
+
 #[async_trait]
 pub trait Backend {
+    /// Name of the backend, e.g. `github`.
+    fn name(&self) -> &'static str;
+
+    /// Check whether araki has credentials for the backend. The credentials may still be
+    /// rejected by the backend.
+    fn is_authenticated(&self) -> bool;
+
+    /// Get the user araki is authenticated as.
+    async fn get_user(&self) -> Result<BackendUser, BackendError>;
+
+    /// Email address to attribute commits to for a user without a public email address.
+    ///
+    /// * `user`: Backend user
+    fn noreply_email(&self, user: &BackendUser) -> String;
+
     /// Check if a lockspec exists under an org.
     ///
     /// * `org`: Organization containing lockspec repositories
@@ -35,6 +62,12 @@ pub trait Backend {
     /// * `name`: Name of the repository (i.e. the name of the lockspec)
     async fn create_repository(&self, org: &str, name: &str) -> Result<(), BackendError>;
 
+    /// Delete a repository under an org.
+    ///
+    /// * `org`: Organization containing the repository
+    /// * `name`: Name of the repository (i.e. the name of the lockspec)
+    async fn delete_repository(&self, org: &str, name: &str) -> Result<(), BackendError>;
+
     /// Log in to the backend.
     async fn login(&self) -> Result<(), BackendError>;
 
@@ -53,6 +86,11 @@ pub trait Backend {
     ///
     /// * `path`: Suffix to join with the API url to send the request to
     fn post(&self, path: &str) -> Result<RequestBuilder, BackendError>;
+
+    /// Create an authenticated DELETE request builder.
+    ///
+    /// * `path`: Suffix to join with the API url to send the request to
+    fn delete(&self, path: &str) -> Result<RequestBuilder, BackendError>;
 }
 
 pub struct GitHubBackend {
@@ -65,6 +103,22 @@ pub type BackendError = Box<dyn Error + Send + Sync>;
 
 #[async_trait]
 impl Backend for GitHubBackend {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+    fn is_authenticated(&self) -> bool {
+        self.client.is_some()
+    }
+    fn noreply_email(&self, user: &BackendUser) -> String {
+        format!("{}@users.noreply.github.com", user.login)
+    }
+    async fn get_user(&self) -> Result<BackendUser, BackendError> {
+        Ok(send(self.get("/user")?)
+            .await?
+            .error_for_status()?
+            .json::<BackendUser>()
+            .await?)
+    }
     fn get(&self, path: &str) -> Result<RequestBuilder, BackendError> {
         Ok(self
             .client
@@ -79,10 +133,15 @@ impl Backend for GitHubBackend {
             .ok_or("Please authenticate with `araki auth login` before continuing.")?
             .post(self.api_url.join(path)?))
     }
+    fn delete(&self, path: &str) -> Result<RequestBuilder, BackendError> {
+        Ok(self
+            .client
+            .as_ref()
+            .ok_or("Please authenticate with `araki auth login` before continuing.")?
+            .delete(self.api_url.join(path)?))
+    }
     async fn is_existing_lockspec(&self, org: &str, name: &str) -> Result<bool, BackendError> {
-        let resp = self
-            .get(format!("/repos/{org}/{name}").as_str())?
-            .send()
+        let resp = send(self.get(format!("/repos/{org}/{name}").as_str())?)
             .await?
             .json::<HashMap<String, String>>()
             .await?;
@@ -94,11 +153,20 @@ impl Backend for GitHubBackend {
             name: name.to_string(),
             private: true,
         };
-        let result = self
-            .post(format!("/orgs/{org}/repos").as_str())?
-            .body(serde_json::to_string(&body)?)
-            .send()
-            .await?;
+        let result = send(
+            self.post(format!("/orgs/{org}/repos").as_str())?
+                .body(serde_json::to_string(&body)?),
+        )
+        .await?;
+
+        if result.status().is_success() {
+            Ok(())
+        } else {
+            Err(result.text().await?.into())
+        }
+    }
+    async fn delete_repository(&self, org: &str, name: &str) -> Result<(), BackendError> {
+        let result = send(self.delete(format!("/repos/{org}/{name}").as_str())?).await?;
 
         if result.status().is_success() {
             Ok(())
@@ -121,12 +189,12 @@ impl Backend for GitHubBackend {
     async fn login(&self) -> Result<(), BackendError> {
         let resp = Self::request_device_code().await?;
 
-        println!(
+        eprintln!(
             "{}{}",
             style("Please visit: ").bold().yellow(),
             resp.verification_uri
         );
-        println!(
+        eprintln!(
             "{}{}",
             style("and enter code: ").bold().yellow(),
             resp.user_code
@@ -208,9 +276,7 @@ impl GitHubBackend {
             &[("client_id", Self::CLIENT_ID), ("scope", "repo admin:org")],
         )?;
 
-        let response = client
-            .post(url)
-            .send()
+        let response = send(client.post(url))
             .await?
             .error_for_status()?
             .json::<GitHubDeviceCodeResponse>()
@@ -238,9 +304,7 @@ impl GitHubBackend {
             ],
         )?;
 
-        Ok(client
-            .post(url)
-            .send()
+        Ok(send(client.post(url))
             .await?
             .json::<serde_json::Value>()
             .await?)
@@ -253,14 +317,9 @@ impl GitHubBackend {
     ///   add a 5s delay to this before the next poll
     async fn poll_for_token(device_code: &str, interval: Duration) -> Result<(), BackendError> {
         loop {
-            let response = match Self::request_token(device_code).await {
-                Ok(resp) => resp,
-                Err(err) => {
-                    eprintln!("ERROR: {err}");
-                    std::process::exit(1);
-                }
-            };
+            let response = Self::request_token(device_code).await?;
             let error = response.get("error");
+            debug!(error:? = error; "Polled for a GitHub token");
 
             match error {
                 Some(val) if val == "authorization_pending" => {
@@ -305,3 +364,94 @@ impl GitHubBackend {
 pub fn get_current_backend() -> Result<impl Backend, BackendError> {
     GitHubBackend::new()
 }
+
+/// Query parameters whose values are credentials, and must never be logged.
+const SECRET_QUERY_PARAMS: &[&str] = &[
+    "access_token",
+    "client_secret",
+    "code",
+    "device_code",
+    "refresh_token",
+];
+
+/// Send a request, logging it and its response with any credentials redacted.
+///
+/// * `request`: Request to send
+async fn send(request: RequestBuilder) -> Result<Response, BackendError> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let url = redact_url(request.url());
+    debug!(method:% = request.method(), url:% = url; "Sending request");
+    trace!(headers:? = redact_headers(request.headers()); "Request headers");
+
+    let response = client.execute(request).await?;
+    debug!(status = response.status().as_u16(), url:% = url; "Received response");
+    trace!(headers:? = redact_headers(response.headers()); "Response headers");
+    Ok(response)
+}
+
+/// Replace the values of query parameters which are credentials with a placeholder.
+///
+/// * `url`: URL to redact
+fn redact_url(url: &Url) -> Url {
+    let mut redacted = url.clone();
+    if url.query().is_none() {
+        return redacted;
+    }
+    redacted
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(url.query_pairs().map(|(key, value)| {
+            if SECRET_QUERY_PARAMS.contains(&key.as_ref()) {
+                (key, "[redacted]".into())
+            } else {
+                (key, value)
+            }
+        }));
+    redacted
+}
+
+/// Replace the values of headers which are credentials with a placeholder.
+///
+/// * `headers`: Headers to redact
+fn redact_headers(headers: &header::HeaderMap) -> header::HeaderMap {
+    let mut redacted = headers.clone();
+    for name in [
+        header::AUTHORIZATION,
+        header::COOKIE,
+        header::PROXY_AUTHORIZATION,
+        header::SET_COOKIE,
+    ] {
+        if redacted.contains_key(&name) {
+            redacted.insert(name, header::HeaderValue::from_static("[redacted]"));
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_credentials() {
+        let url = Url::parse(
+            "https://github.com/login/oauth/access_token?client_id=abc&device_code=secret",
+        )
+        .unwrap();
+        let redacted = redact_url(&url).to_string();
+        assert!(redacted.contains("client_id=abc"));
+        assert!(!redacted.contains("secret"));
+
+        let headers = GitHubBackend::make_authenticated_request_headers("ghu_secret").unwrap();
+        let redacted = format!("{:?}", redact_headers(&headers));
+        assert!(!redacted.contains("ghu_secret"));
+        assert!(redacted.contains("application/vnd.github+json"));
+
+        let mut headers = header::HeaderMap::new();
+        headers.append(header::SET_COOKIE, "session=secret".parse().unwrap());
+        headers.append(header::SET_COOKIE, "user=secret".parse().unwrap());
+        let redacted = format!("{:?}", redact_headers(&headers));
+        assert!(!redacted.contains("secret"));
+    }
+}
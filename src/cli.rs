@@ -1,10 +1,19 @@
 pub mod auth;
+pub mod check;
 pub mod checkout;
 pub mod clone;
+pub mod diff;
+pub mod envs;
+pub mod graph;
 pub mod init;
 pub mod list;
+pub mod logging;
+pub mod output;
+pub mod prompt;
 pub mod pull;
 pub mod push;
 pub mod shell;
 pub mod shim;
+pub mod stash;
+pub mod status;
 pub mod tag;
@@ -1,8 +1,9 @@
-use crate::backends::Backend;
+use araki::ArakiError;
+use araki::backends::{self, Backend};
 use clap::Parser;
-use std::process::exit;
+use serde_json::json;
 
-use crate::backends;
+use crate::cli::output::{OutputFormat, print_json};
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -15,21 +16,45 @@ pub struct Args {
 pub enum AuthSubcommand {
     // Log in to the configured backend
     Login,
+
+    /// Show whether araki is authenticated with the configured backend, and as which user
+    Status,
 }
 
-pub async fn execute(args: Args) {
+pub async fn execute(args: Args, output: OutputFormat) -> Result<(), ArakiError> {
+    let backend = backends::get_current_backend()
+        .map_err(ArakiError::backend("Unable to get the current backend"))?;
     match args.subcommand {
         AuthSubcommand::Login => {
-            let backend = backends::get_current_backend().unwrap_or_else(|err| {
-                eprintln!("Unable to get the current backend: {err}");
-                exit(1);
-            });
-            backend.login().await.unwrap_or_else(|err| {
-                eprintln!("Unable to login: {err}");
-                exit(1);
-            });
+            backend
+                .login()
+                .await
+                .map_err(ArakiError::backend("Unable to login"))?;
 
-            println!("Successfully authenticated.");
+            eprintln!("Successfully authenticated.");
+        }
+        AuthSubcommand::Status => {
+            // A cached token may have expired or been revoked, so ask the backend who it belongs to
+            let user = if backend.is_authenticated() {
+                backend.get_user().await.ok()
+            } else {
+                None
+            };
+            if output == OutputFormat::Json {
+                return print_json(&json!({
+                    "backend": backend.name(),
+                    "authenticated": user.is_some(),
+                    "user": user,
+                }));
+            }
+            match user {
+                Some(user) => println!("Logged in to {} as {}.", backend.name(), user.login),
+                None => println!(
+                    "Not logged in to {}. Run `araki auth login` to authenticate.",
+                    backend.name()
+                ),
+            }
         }
     }
+    Ok(())
 }
@@ -0,0 +1,18 @@
+use araki::ArakiError;
+use araki::lockspecs::LockSpec;
+use araki::workspace;
+use clap::Parser;
+use std::env::current_dir;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {}
+
+pub fn execute(_args: Args) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    // Outside of a workspace, the lockspec in the current directory can still be checked
+    let dir = workspace::find_root(&cwd).unwrap_or(cwd);
+    let lockspec = LockSpec::from_path(&dir).map_err(|_| ArakiError::NoLockSpec(dir))?;
+    workspace::check_lockspec(&lockspec)?;
+    println!("{} is consistent.", lockspec);
+    Ok(())
+}
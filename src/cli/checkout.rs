@@ -1,41 +1,55 @@
+use araki::workspace::CheckoutOptions;
+use araki::{ArakiError, Workspace};
 use clap::Parser;
-use std::process::{Command, exit};
-
-use crate::common;
+use std::env::current_dir;
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
-    // name of the tag
-    #[arg(help = "Name of the tag")]
-    tag: String,
-}
+    /// Tag, branch or commit to check out. Also accepts `latest` for the main branch,
+    /// `latest~N` for N commits before it, `previous` for the tag checked out before the
+    /// current one, and REVISION@{DATE} for REVISION as it was on a date
+    #[arg(required_unless_present = "as_of", value_name = "REVISION")]
+    tag: Option<String>,
 
-pub fn execute(args: Args) {
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
+    /// Check out the environment as it was on a date, e.g. 2025-06-01 or 2025-06-01T12:00.
+    /// Applies to the main branch unless a revision is given
+    #[arg(long, value_name = "DATE")]
+    as_of: Option<String>,
 
-    let git_ref = if args.tag == "latest" {
-        repo.find_reference("refs/heads/main")
-            .expect("No tag found")
-    } else {
-        repo.find_reference(&format!("refs/tags/{}", args.tag))
-            .expect("No tag found")
-    };
+    /// Discard uncommitted changes to the lockspec
+    #[arg(long, conflicts_with = "stash")]
+    force: bool,
+
+    /// Save uncommitted changes to the lockspec; restore them with `araki stash pop`
+    #[arg(long)]
+    stash: bool,
 
-    let git_ref_object = git_ref.peel(git2::ObjectType::Commit).unwrap();
-    let commit = git_ref_object
-        .as_commit()
-        .ok_or_else(|| git2::Error::from_str("Tag did not peel to a commit"))
-        .unwrap();
-    repo.checkout_tree(commit.as_object(), None)
-        .expect("Unable to checkout tag");
-    repo.set_head_detached(commit.id())
-        .expect("Unable to set head");
+    /// Only check out the lockspec files, without installing the environment
+    #[arg(long, conflicts_with = "environment")]
+    no_install: bool,
 
-    let _ = Command::new("pixi")
-        .arg("install")
-        .output()
-        .expect("Failed to execute command");
+    /// Only install this environment of the lockspec, e.g. a pixi environment
+    #[arg(long, value_name = "NAME")]
+    environment: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let mut workspace = Workspace::discover(cwd)?;
+    let revision = match (args.tag, args.as_of) {
+        (tag, Some(date)) => format!("{}@{{{date}}}", tag.as_deref().unwrap_or("latest")),
+        (Some(tag), None) => tag,
+        (None, None) => unreachable!("clap requires a revision or --as-of"),
+    };
+    let resolved = workspace.checkout(
+        &revision,
+        &CheckoutOptions {
+            force: args.force,
+            stash: args.stash,
+            no_install: args.no_install,
+            environment: args.environment,
+        },
+    )?;
+    println!("Checked out {resolved}");
+    Ok(())
 }
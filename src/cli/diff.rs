@@ -0,0 +1,130 @@
+use araki::diff::{self, ChangeKind, DependencyChange, PackageChange};
+use araki::workspace::{LockSpecDiff, Revision};
+use araki::{ArakiError, Workspace};
+use clap::{Parser, ValueEnum};
+use console::{StyledObject, style};
+use serde_json::{Value, json};
+use std::env::current_dir;
+use std::fmt::Display;
+
+use crate::cli::output::{OutputFormat, print_json};
+
+/// When to color the changes.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color the changes if stdout is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Revision to compare from: a tag, branch, commit, `latest~N`, `previous` or `REV@{DATE}`.
+    /// Defaults to HEAD
+    #[arg(value_name = "REV-A")]
+    from: Option<String>,
+
+    /// Revision to compare to. Defaults to the files in the workspace
+    #[arg(value_name = "REV-B")]
+    to: Option<String>,
+
+    /// Color the changes: `auto` colors them if stdout is a terminal
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+}
+
+pub fn execute(args: Args, output: OutputFormat) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let workspace = Workspace::discover(cwd)?;
+    let diff = workspace.diff(args.from.as_deref(), args.to.as_deref())?;
+
+    if output == OutputFormat::Json {
+        return print_json(&json!({
+            "from": revision_json(args.from.as_deref().unwrap_or("HEAD"), &diff.from),
+            "to": args.to.as_deref().zip(diff.to.as_ref()).map(|(name, to)| revision_json(name, to)),
+            "dependencies": diff.dependencies,
+            "packages": diff.packages,
+        }));
+    }
+
+    match args.color {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => console::set_colors_enabled(true),
+        ColorChoice::Never => console::set_colors_enabled(false),
+    }
+    print_diff(&diff);
+    Ok(())
+}
+
+/// Describe a revision for the JSON output.
+///
+/// * `name`: Revision as given on the command line
+/// * `revision`: Revision it resolved to
+fn revision_json(name: &str, revision: &Revision) -> Value {
+    json!({ "revision": name, "commit": revision.commit.to_string() })
+}
+
+/// Print the changes grouped by environment and platform, dependency specs first.
+fn print_diff(diff: &LockSpecDiff) {
+    let to = match &diff.to {
+        Some(to) => to.to_string(),
+        None => "the working files".to_string(),
+    };
+    if diff.dependencies.is_empty() && diff.packages.is_empty() {
+        println!("No changes between {} and {to}.", diff.from);
+        return;
+    }
+    println!("Changes from {} to {to}:", diff.from);
+
+    if !diff.dependencies.is_empty() {
+        println!("\n{}", style("Dependency specs").bold());
+        print_grouped(&diff.dependencies, |change: &DependencyChange| {
+            (
+                diff::heading(&change.environment, change.platform.as_deref()),
+                change.kind,
+            )
+        });
+    }
+    if !diff.packages.is_empty() {
+        println!(
+            "\n{} ({})",
+            style("Packages").bold(),
+            diff::headline(&diff.packages)
+        );
+        print_grouped(&diff.packages, |change: &PackageChange| {
+            (
+                diff::heading(&change.environment, Some(&change.platform)),
+                change.kind,
+            )
+        });
+    }
+}
+
+/// Print changes one per line, under a heading whenever it differs from the previous change.
+///
+/// * `changes`: Changes, sorted by heading
+/// * `describe`: Heading and kind of a change
+fn print_grouped<T: Display>(changes: &[T], describe: impl Fn(&T) -> (String, ChangeKind)) {
+    let mut group = None;
+    for change in changes {
+        let (heading, kind) = describe(change);
+        if group.as_ref() != Some(&heading) {
+            println!("{heading}");
+            group = Some(heading);
+        }
+        println!("  {}", colored(change.to_string(), kind));
+    }
+}
+
+/// Color a change according to its kind.
+fn colored(line: String, kind: ChangeKind) -> StyledObject<String> {
+    match kind {
+        ChangeKind::Added => style(line).green(),
+        ChangeKind::Removed => style(line).red(),
+        ChangeKind::Upgraded => style(line).cyan(),
+        ChangeKind::Downgraded => style(line).magenta(),
+        ChangeKind::Changed => style(line).yellow(),
+    }
+}
@@ -0,0 +1,54 @@
+use araki::ArakiError;
+use araki::registry::Registry;
+use clap::Parser;
+use console::style;
+use serde_json::json;
+
+use crate::cli::output::{OutputFormat, print_json};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: EnvsSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum EnvsSubcommand {
+    /// List the environments initialized or cloned with araki
+    Ls,
+}
+
+pub fn execute(args: Args, output: OutputFormat) -> Result<(), ArakiError> {
+    match args.subcommand {
+        EnvsSubcommand::Ls => {
+            let registry = Registry::load()?;
+            if output == OutputFormat::Json {
+                let envs: Vec<_> = registry
+                    .envs()
+                    .iter()
+                    .map(|env| {
+                        json!({
+                            "name": env.name,
+                            "path": env.path,
+                            "remote": env.remote,
+                            "exists": env.exists(),
+                        })
+                    })
+                    .collect();
+                return print_json(&json!({ "envs": envs }));
+            }
+
+            println!("Available envs:");
+            for env in registry.envs() {
+                let missing = if env.exists() {
+                    String::new()
+                } else {
+                    style(" (missing)").red().to_string()
+                };
+                println!("* {:<16} {}{missing}", env.name, env.path.display());
+            }
+        }
+    }
+    Ok(())
+}
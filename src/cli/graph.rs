@@ -0,0 +1,149 @@
+use araki::workspace::HistoryEntry;
+use git2::Oid;
+
+/// A line of a commit graph.
+#[derive(Debug)]
+pub enum GraphLine<'a> {
+    /// The line of a commit, e.g. `| * `
+    Commit {
+        graph: String,
+        entry: &'a HistoryEntry,
+    },
+    /// A line joining lanes which fork or merge between two commits, e.g. `|/`
+    Edge(String),
+}
+
+/// Lay out the history of a workspace as a graph in the style of `git log --graph`: each
+/// commit is drawn in a lane, with lanes forking at merges and joining at common parents.
+///
+/// * `history`: Commits ordered children first, as returned by `Workspace::history`
+pub fn layout(history: &[HistoryEntry]) -> Vec<GraphLine<'_>> {
+    // The commit each lane is waiting for
+    let mut lanes: Vec<Option<Oid>> = vec![];
+    let mut lines = vec![];
+    for entry in history {
+        let col = lanes
+            .iter()
+            .position(|lane| *lane == Some(entry.commit))
+            .or_else(|| lanes.iter().position(Option::is_none))
+            .unwrap_or_else(|| {
+                lanes.push(None);
+                lanes.len() - 1
+            });
+        lines.push(GraphLine::Commit {
+            graph: draw(&lanes, |i| if i == col { Some('*') } else { None }),
+            entry,
+        });
+
+        let mut opened = vec![];
+        let mut closed = vec![];
+        lanes[col] = entry.parents.first().copied();
+        if let Some(parent) = lanes[col]
+            && let Some(other) = lanes
+                .iter()
+                .enumerate()
+                .position(|(i, lane)| i != col && *lane == Some(parent))
+        {
+            // Both lanes lead to the same parent, so the rightmost one joins the other
+            let join = col.max(other);
+            lanes[join] = None;
+            closed.push(join);
+        }
+        for parent in entry.parents.iter().skip(1) {
+            if lanes.contains(&Some(*parent)) {
+                continue;
+            }
+            let lane = match lanes.iter().skip(col + 1).position(Option::is_none) {
+                Some(free) => col + 1 + free,
+                None => {
+                    lanes.push(None);
+                    lanes.len() - 1
+                }
+            };
+            lanes[lane] = Some(*parent);
+            opened.push(lane);
+        }
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+
+        if !opened.is_empty() || !closed.is_empty() {
+            let mut edge = draw(&lanes, |i| opened.contains(&i).then_some(' '));
+            let mut chars: Vec<char> = edge.chars().collect();
+            for (changed, symbol) in [(&opened, '\\'), (&closed, '/')] {
+                for lane in changed {
+                    let at = 2 * lane - 1;
+                    if chars.len() <= at {
+                        chars.resize(at + 1, ' ');
+                    }
+                    chars[at] = symbol;
+                }
+            }
+            edge = chars.into_iter().collect::<String>().trim_end().to_string();
+            lines.push(GraphLine::Edge(edge));
+        }
+    }
+    lines
+}
+
+/// Draw a line of lanes: `|` for each lane waiting for a commit, unless `symbol` overrides it.
+///
+/// * `lanes`: Commit each lane is waiting for
+/// * `symbol`: Character to draw for a lane instead of the default
+fn draw(lanes: &[Option<Oid>], symbol: impl Fn(usize) -> Option<char>) -> String {
+    let line: Vec<String> = lanes
+        .iter()
+        .enumerate()
+        .map(|(i, lane)| {
+            symbol(i)
+                .unwrap_or(if lane.is_some() { '|' } else { ' ' })
+                .to_string()
+        })
+        .collect();
+    line.join(" ").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jiff::Timestamp;
+
+    fn entry(id: u8, parents: &[u8]) -> HistoryEntry {
+        let oid = |id: u8| Oid::from_bytes(&[id; 20]).unwrap();
+        HistoryEntry {
+            commit: oid(id),
+            parents: parents.iter().copied().map(oid).collect(),
+            time: Timestamp::UNIX_EPOCH,
+            summary: String::new(),
+            branches: vec![],
+            tags: vec![],
+            head: false,
+        }
+    }
+
+    fn render(history: &[HistoryEntry]) -> Vec<String> {
+        layout(history)
+            .into_iter()
+            .map(|line| match line {
+                GraphLine::Commit { graph, .. } => graph,
+                GraphLine::Edge(edge) => edge,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_layout() {
+        // A branch forked from the first commit
+        let forked = [entry(3, &[1]), entry(2, &[1]), entry(1, &[])];
+        assert_eq!(render(&forked), ["*", "| *", "|/", "*"]);
+
+        // A merge of two lines of history
+        let merged = [
+            entry(4, &[2, 3]),
+            entry(3, &[1]),
+            entry(2, &[1]),
+            entry(1, &[]),
+        ];
+        assert_eq!(render(&merged), ["*", "|\\", "| *", "* |", "|/", "*"]);
+    }
+}
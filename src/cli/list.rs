@@ -1,62 +1,183 @@
+use araki::remote::parse_repo_arg;
+use araki::workspace::{HistoryEntry, ListOptions, TagInfo, TagSort};
+use araki::{ArakiError, Workspace};
 use clap::Parser;
-use git2::Tag;
-use std::process::{Command, exit};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use console::style;
+use jiff::tz::TimeZone;
+use serde_json::json;
+use std::env::current_dir;
 
-use crate::common;
+use crate::cli::graph::{self, GraphLine};
+use crate::cli::output::{OutputFormat, print_json};
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
+    /// URL or <github org>/<repo name> of a lockspec repository to list the tags of, instead of
+    /// the current workspace. Requires --remote
+    #[arg(value_name = "NAME", requires = "remote")]
+    repo: Option<String>,
+
+    /// List the tags of the lockspec repository given by NAME without cloning it
+    #[arg(long, requires = "repo", conflicts_with = "tree")]
+    remote: bool,
+
     #[arg(long, help = "Switch to print out the list of checkpoints as a tree")]
     tree: bool,
+
+    /// Order of the tags: alphabetical, newest first or highest version first
+    #[arg(
+        long,
+        default_value = "name",
+        conflicts_with = "tree",
+        value_parser = PossibleValuesParser::new(["name", "date", "semver"])
+            .map(|sort| sort.parse::<TagSort>().unwrap()),
+    )]
+    sort: TagSort,
+
+    /// Only list the tags whose name matches a glob pattern, e.g. "v1.*"
+    #[arg(long, value_name = "GLOB", conflicts_with = "tree")]
+    filter: Option<String>,
+
+    /// List at most this many tags, after sorting
+    #[arg(long, value_name = "N", conflicts_with = "tree")]
+    limit: Option<usize>,
 }
 
-pub fn execute(args: Args) {
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
+pub fn execute(args: Args, output: OutputFormat) -> Result<(), ArakiError> {
+    let options = ListOptions {
+        sort: args.sort,
+        filter: args.filter,
+        limit: args.limit,
+    };
+    if let Some(repo) = args.repo {
+        let remote = parse_repo_arg(&repo).map_err(|reason| ArakiError::InvalidRemote {
+            remote: repo.clone(),
+            reason,
+        })?;
+        let tags = Workspace::list_remote(&remote, &options)?;
+        return print_tags_as(&tags, output);
+    }
 
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let workspace = Workspace::discover(cwd)?;
     if args.tree {
-        // TODO: use the repo object to get the tree
-        let tree_output = Command::new("git")
-            .arg("tree")
-            .output()
-            .expect("Failed to execute command");
-        let tree_stdout = String::from_utf8_lossy(&tree_output.stdout);
-        println!("{}", tree_stdout);
+        if output == OutputFormat::Json {
+            return Err(ArakiError::Other(
+                "--tree can't be combined with --output json".to_string(),
+            ));
+        }
+        print_tree(&workspace.history()?);
+        Ok(())
     } else {
-        let tags = repo.tag_names(Some("*")).unwrap();
+        print_tags_as(&workspace.list(&options)?, output)
+    }
+}
 
-        for name_w in tags.iter() {
-            let name = name_w.unwrap();
-            let obj = repo.revparse_single(name).expect("Unable to get tag");
+/// Print the tags in the requested output format.
+fn print_tags_as(tags: &[TagInfo], output: OutputFormat) -> Result<(), ArakiError> {
+    if output == OutputFormat::Json {
+        print_json(&json!({ "tags": tags }))
+    } else {
+        print_tags(tags);
+        Ok(())
+    }
+}
 
-            if let Some(tag) = obj.as_tag() {
-                print_tag(tag);
-            } else {
-                print_name(name);
+/// Print the history of the workspace as a graph, with the branches and tags of each commit.
+fn print_tree(history: &[HistoryEntry]) {
+    let lines = graph::layout(history);
+    let width = lines
+        .iter()
+        .filter_map(|line| match line {
+            GraphLine::Commit { graph, .. } => Some(graph.chars().count()),
+            GraphLine::Edge(_) => None,
+        })
+        .max()
+        .unwrap_or_default();
+    for line in lines {
+        match line {
+            GraphLine::Commit { graph, entry } => {
+                let id = entry.commit.to_string();
+                let date = entry.time.to_zoned(TimeZone::system()).date();
+                println!(
+                    "{graph:<width$} {} {date} {}{}",
+                    style(&id[..7]).yellow(),
+                    labels(entry),
+                    entry.summary
+                );
             }
+            GraphLine::Edge(edge) => println!("{edge}"),
         }
     }
 }
 
-fn print_tag(tag: &Tag) {
-    print!("{:<16}", tag.name().unwrap());
-    print_list_lines(tag.message());
-}
-
-fn print_name(name: &str) {
-    println!("{}", name);
+/// Format the labels of a commit, e.g. `(HEAD, main, tag: v1) `, or nothing if it has none.
+fn labels(entry: &HistoryEntry) -> String {
+    let head = entry.head.then(|| style("HEAD".to_string()).cyan().bold());
+    let branches = entry
+        .branches
+        .iter()
+        .map(|branch| style(branch.clone()).green());
+    let tags = entry
+        .tags
+        .iter()
+        .map(|tag| style(format!("tag: {tag}")).yellow().bold());
+    let labels: Vec<String> = head
+        .into_iter()
+        .chain(branches)
+        .chain(tags)
+        .map(|label| label.to_string())
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("({}) ", labels.join(", "))
+    }
 }
 
-fn print_list_lines(message: Option<&str>) {
-    let message = match message {
-        Some(s) => s,
-        _none => return,
-    };
-    let mut lines = message.lines().filter(|l| !l.trim().is_empty());
-    if let Some(first) = lines.next() {
-        print!("{}", first);
+/// Print the tags as a table, marking the one which is checked out.
+fn print_tags(tags: &[TagInfo]) {
+    let name_width = tags
+        .iter()
+        .map(|tag| tag.name.len())
+        .max()
+        .unwrap_or_default();
+    let author_width = tags
+        .iter()
+        .map(|tag| tag.author.chars().count())
+        .max()
+        .unwrap_or_default();
+    for tag in tags {
+        let marker = if tag.current {
+            style("*").green().bold().to_string()
+        } else {
+            " ".to_string()
+        };
+        let name = format!("{:<name_width$}", tag.name);
+        let name = if tag.current {
+            style(name).green().bold()
+        } else {
+            style(name)
+        };
+        let packages = tag
+            .packages
+            .map_or("?".to_string(), |packages| packages.to_string());
+        let remote = match tag.on_remote {
+            Some(true) => style("pushed").green(),
+            Some(false) => style("local ").yellow(),
+            None => style("?     ").dim(),
+        };
+        let description = tag
+            .message
+            .as_deref()
+            .and_then(|message| message.lines().find(|line| !line.trim().is_empty()))
+            .unwrap_or_default();
+        println!(
+            "{marker} {name} {} {} {:<author_width$} {packages:>4} pkgs  {remote} {description}",
+            tag.date.to_zoned(TimeZone::system()).date(),
+            style(&tag.commit[..7]).yellow(),
+            tag.author,
+        );
     }
-    println!();
 }
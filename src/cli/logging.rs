@@ -0,0 +1,81 @@
+use araki::ArakiError;
+use env_logger::{Builder, Target, WriteStyle};
+use git2::TraceLevel;
+use log::{Level, LevelFilter};
+use std::env;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// Environment variable overriding the verbosity flags, using `env_logger` filter syntax, e.g.
+/// `ARAKI_LOG=araki::backends=trace,git2=debug`.
+pub const LOG_ENV_VAR: &str = "ARAKI_LOG";
+
+/// Set up logging for the CLI.
+///
+/// Only araki's own messages (and libgit2's trace messages) are affected by `verbose`, up to
+/// `-vv`; `-vvv` also enables the logging of every dependency.
+///
+/// * `verbose`: Number of times `-v` was passed
+/// * `quiet`: Only log errors
+/// * `log_file`: Write the log to this file instead of stderr
+pub fn init(verbose: u8, quiet: bool, log_file: Option<&Path>) -> Result<(), ArakiError> {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+
+    let mut builder = Builder::new();
+    builder
+        .filter_level(if verbose >= 3 {
+            level
+        } else {
+            level.min(LevelFilter::Warn)
+        })
+        .filter_module("araki", level)
+        .filter_module("git2", level);
+    if let Ok(filters) = env::var(LOG_ENV_VAR) {
+        builder.parse_filters(&filters);
+    }
+    if let Some(path) = log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(ArakiError::io(format!(
+                "Unable to open the log file {path:?}"
+            )))?;
+        builder
+            .target(Target::Pipe(Box::new(file)))
+            .write_style(WriteStyle::Never);
+    }
+    builder.init();
+
+    let trace_level = match log::max_level() {
+        LevelFilter::Off => TraceLevel::None,
+        LevelFilter::Error => TraceLevel::Error,
+        LevelFilter::Warn => TraceLevel::Warn,
+        LevelFilter::Info => TraceLevel::Info,
+        LevelFilter::Debug => TraceLevel::Debug,
+        LevelFilter::Trace => TraceLevel::Trace,
+    };
+    if let Err(err) = git2::trace_set(trace_level, log_git2_trace) {
+        log::debug!("libgit2 tracing is unavailable: {err}");
+    }
+    Ok(())
+}
+
+/// Forward a libgit2 trace message to the logger.
+fn log_git2_trace(level: TraceLevel, message: &[u8]) {
+    let level = match level {
+        TraceLevel::None => return,
+        TraceLevel::Fatal | TraceLevel::Error => Level::Error,
+        TraceLevel::Warn => Level::Warn,
+        TraceLevel::Info => Level::Info,
+        TraceLevel::Debug => Level::Debug,
+        TraceLevel::Trace => Level::Trace,
+    };
+    log::log!(target: "git2", level, "{}", String::from_utf8_lossy(message));
+}
@@ -0,0 +1,23 @@
+use araki::ArakiError;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Format of the results printed to stdout by read commands.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON, following the schemas documented in the README
+    Json,
+}
+
+/// Print a value to stdout as pretty-printed JSON.
+///
+/// * `value`: Value to print
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), ArakiError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|err| ArakiError::Other(format!("Unable to serialize the output: {err}")))?;
+    println!("{json}");
+    Ok(())
+}
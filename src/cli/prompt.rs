@@ -0,0 +1,43 @@
+use araki::ArakiError;
+use console::Term;
+
+/// Check whether questions can be asked, i.e. whether stderr is a terminal.
+pub fn is_interactive() -> bool {
+    Term::stderr().is_term()
+}
+
+/// Ask the user a yes/no question on stderr, defaulting to no.
+///
+/// Fails if stderr isn't a terminal, since nobody could answer the question.
+///
+/// * `question`: Question to ask, without the `[y/N]` suffix
+pub fn confirm(question: &str) -> Result<bool, ArakiError> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(ArakiError::Other(format!(
+            "{question} Pass --yes to confirm when not running interactively."
+        )));
+    }
+    term.write_str(&format!("{question} [y/N] "))
+        .and_then(|_| term.read_line())
+        .map(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+        .map_err(ArakiError::io("Unable to read the answer"))
+}
+
+/// Ask the user for a line of text on stderr, returning it without surrounding whitespace.
+///
+/// Fails if stderr isn't a terminal, since nobody could answer the question.
+///
+/// * `question`: Question to ask
+pub fn input(question: &str) -> Result<String, ArakiError> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(ArakiError::Other(format!(
+            "{question} Unable to ask when not running interactively."
+        )));
+    }
+    term.write_str(&format!("{question} "))
+        .and_then(|_| term.read_line())
+        .map(|answer| answer.trim().to_string())
+        .map_err(ArakiError::io("Unable to read the answer"))
+}
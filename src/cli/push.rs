@@ -1,25 +1,26 @@
+use araki::workspace::PushOptions;
+use araki::{ArakiError, Workspace};
 use clap::Parser;
-use std::process::exit;
-
-use crate::common;
+use std::env::current_dir;
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
     /// name of the tag
     #[arg()]
     tag: String,
+
+    /// Skip checking that the tagged lockfile is consistent with the tagged specfile
+    #[arg(long)]
+    no_verify: bool,
 }
 
-pub fn execute(args: Args) {
-    common::git_push(
-        "origin",
-        &[
-            "refs/heads/main",
-            format!("refs/tags/{}", args.tag).as_str(),
-        ],
+pub fn execute(args: Args) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let workspace = Workspace::discover(cwd)?;
+    workspace.push(
+        &args.tag,
+        &PushOptions {
+            no_verify: args.no_verify,
+        },
     )
-    .unwrap_or_else(|err| {
-        eprintln!("Unable to push to remote: {err}");
-        exit(1);
-    })
 }
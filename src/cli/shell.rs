@@ -11,7 +11,8 @@ use std::{
     str::FromStr,
 };
 
-use crate::common::get_araki_bin_dir;
+use araki::ArakiError;
+use araki::common::get_araki_bin_dir;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -186,7 +187,7 @@ impl Shell {
     }
 }
 
-pub fn execute(args: Args) {
+pub fn execute(args: Args) -> Result<(), ArakiError> {
     match args.subcommand {
         ShellSubcommand::Init(shell_arg) => {
             let shell: Shell = shell_arg
@@ -198,10 +199,8 @@ pub fn execute(args: Args) {
                 })
                 .unwrap_or_else(Shell::detect);
 
-            match shell.update_shell_config() {
-                Ok(_) => println!("{shell} configuration updated."),
-                Err(error) => eprintln!("{error}"),
-            };
+            shell.update_shell_config()?;
+            println!("{shell} configuration updated.");
         }
         ShellSubcommand::Generate(shell_arg) => {
             let shell: Shell = shell_arg
@@ -213,7 +212,8 @@ pub fn execute(args: Args) {
                 })
                 .unwrap_or_else(Shell::detect);
 
-            let _ = shell.print_env().map_err(|error| eprintln!("{error}"));
+            shell.print_env()?;
         }
     }
+    Ok(())
 }
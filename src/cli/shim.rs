@@ -1,8 +1,11 @@
 use clap::Parser;
-use std::env;
+use std::env::{self, current_dir};
 use std::process::{Command, exit};
 
-use crate::common::get_araki_bin_dir;
+use araki::ArakiError;
+use araki::common::get_araki_bin_dir;
+use araki::lockspecs::LockSpec;
+use araki::workspace;
 
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
@@ -25,43 +28,71 @@ fn strip_araki_shim_path(path: &str, shim_path: &str) -> Result<String, String>
         .join(":"))
 }
 
-pub fn execute(args: Args) {
-    let value = env::var("ARAKI_OVERRIDE_SHIM").unwrap_or("false".to_string());
-    if value.trim() == "1" {
-        // Run the requested command using the modified PATH
-        let current_path = env::var_os("PATH");
+/// Check whether the lockspec in the current directory (or the workspace containing it) is
+/// managed by the given tool, in which case the tool is allowed to run without overriding the
+/// shim.
+///
+/// * `tool`: Name of the shimmed tool, e.g. `uv`
+fn is_exempt(tool: &str) -> bool {
+    current_dir()
+        .ok()
+        .and_then(|cwd| LockSpec::from_path(workspace::find_root(&cwd).unwrap_or(cwd)).ok())
+        .is_some_and(|lockspec| lockspec.format.exempt_tools().contains(&tool))
+}
 
-        let shim_path = get_araki_bin_dir().unwrap_or_else(|err| {
-            eprintln!("Unable to get the araki bin directory: {err}");
-            exit(1);
-        });
+/// Run the shimmed tool with the araki bin directory stripped from PATH. If the tool fails,
+/// araki exits with its exit code.
+///
+/// * `args`: The tool to be run `pip`, etc... followed by any trailing arguments
+fn run_tool(args: &[String]) -> Result<(), ArakiError> {
+    let current_path = env::var_os("PATH");
+
+    let shim_path = get_araki_bin_dir().map_err(|err| {
+        ArakiError::Other(format!("Unable to get the araki bin directory: {err}"))
+    })?;
 
-        // Extract the tool to be run `pip`, etc... from the argument list passed to araki.
-        // Call the tool and pass in any trailing arguments using the stripped PATH env variable.
-        if let [tool, arguments @ ..] = args.args.as_slice() {
-            let mut command = Command::new(tool);
-            if let Some(path) = current_path {
-                match strip_araki_shim_path(&path.to_string_lossy(), &shim_path.to_string_lossy()) {
-                    Ok(new_env) => command.env("PATH", new_env),
-                    Err(err) => {
-                        eprintln!("Unable to strip the araki shim path from PATH:\n{err}");
-                        return;
-                    }
-                };
-            }
-            let _ = command
-                .args(arguments)
-                .status()
-                .map_err(|err| eprintln!("Error running command {tool}: {err}"));
-        } else {
-            eprintln!("Could not destructure the command you passed.");
+    // Extract the tool to be run `pip`, etc... from the argument list passed to araki.
+    // Call the tool and pass in any trailing arguments using the stripped PATH env variable.
+    if let [tool, arguments @ ..] = args {
+        let mut command = Command::new(tool);
+        if let Some(path) = current_path {
+            let new_env =
+                strip_araki_shim_path(&path.to_string_lossy(), &shim_path.to_string_lossy())
+                    .map_err(|err| {
+                        ArakiError::Other(format!(
+                            "Unable to strip the araki shim path from PATH:\n{err}"
+                        ))
+                    })?;
+            command.env("PATH", new_env);
+        }
+        let status = command
+            .args(arguments)
+            .status()
+            .map_err(ArakiError::io(format!("Error running command {tool}")))?;
+        // Exit like the tool did, so that scripts calling it through the shim see its failures
+        if !status.success() {
+            exit(status.code().unwrap_or(1));
         }
+        Ok(())
+    } else {
+        Err(ArakiError::Other(
+            "Could not destructure the command you passed.".to_string(),
+        ))
+    }
+}
+
+pub fn execute(args: Args) -> Result<(), ArakiError> {
+    let value = env::var("ARAKI_OVERRIDE_SHIM").unwrap_or("false".to_string());
+    let exempt = args.args.first().is_some_and(|tool| is_exempt(tool));
+    if value.trim() == "1" || exempt {
+        // Run the requested command using the modified PATH
+        run_tool(&args.args)
     } else {
         let passed_args = args.args.join(" ");
-        eprintln!(
+        Err(ArakiError::Other(format!(
             "Unable to run {passed_args}; use araki for environment management. \
             Set ARAKI_OVERRIDE_SHIM=1 to run the command anyway."
-        )
+        )))
     }
 }
 
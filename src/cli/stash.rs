@@ -0,0 +1,28 @@
+use araki::{ArakiError, Workspace};
+use clap::Parser;
+use std::env::current_dir;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: StashSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum StashSubcommand {
+    /// Restore the changes saved with `araki checkout --stash`
+    Pop,
+}
+
+pub fn execute(args: Args) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let mut workspace = Workspace::discover(cwd)?;
+    match args.subcommand {
+        StashSubcommand::Pop => {
+            workspace.stash_pop()?;
+            println!("Restored the stashed changes");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use araki::common::FileStatus;
+use araki::{ArakiError, Workspace};
+use clap::Parser;
+use console::style;
+use serde_json::json;
+use std::env::current_dir;
+
+use crate::cli::output::{OutputFormat, print_json};
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {}
+
+pub fn execute(_args: Args, output: OutputFormat) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let workspace = Workspace::discover(cwd)?;
+    let changes = workspace.status()?;
+
+    if output == OutputFormat::Json {
+        return print_json(&json!({ "changes": changes }));
+    }
+
+    if changes.is_empty() {
+        println!("No changes since the last tag.");
+        return Ok(());
+    }
+    println!("Changes since the last tag:");
+    for change in changes {
+        let status = format!("{:<10}", format!("{}:", change.status));
+        let status = match change.status {
+            FileStatus::Added => style(status).green(),
+            FileStatus::Modified => style(status).yellow(),
+            FileStatus::Deleted => style(status).red(),
+        };
+        println!("  {status} {}", change.path.display());
+    }
+    Ok(())
+}
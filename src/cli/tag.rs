@@ -1,77 +1,175 @@
+use araki::diff::{self, PackageChange};
+use araki::identity::Identity;
+use araki::version::TagBump;
+use araki::workspace::TagOptions;
+use araki::{ArakiError, Workspace};
 use clap::Parser;
-use git2::Signature;
-use std::path::Path;
-use std::process::exit;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use std::env::current_dir;
 
-use crate::common;
+use crate::cli::prompt;
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
-    // TODO: name of the environment, defaults to the current active environment
-    // #[arg(short, long, help="Name of target environment. Defaults to the current active environment if available")]
-    // name: Option<String>,
+    /// Name of the tag. Defaults to the next name according to `tag_bump` in the araki config
+    #[arg(conflicts_with_all = ["bump", "calver"], required_if_eq("delete", "true"))]
+    tag: Option<String>,
 
-    // name of the tag
-    #[arg(help = "Name of the tag")]
-    tag: String,
+    /// Delete the tag instead of creating it
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "bump", "calver", "description", "allow_empty", "force", "author", "committer", "sign",
+            "branch"
+        ]
+    )]
+    delete: bool,
+
+    /// Also delete the tag from the remote
+    #[arg(long, requires = "delete")]
+    remote: bool,
+
+    /// Delete the tag without asking for confirmation
+    #[arg(short, long, requires = "delete")]
+    yes: bool,
+
+    /// Name the tag by incrementing the latest semver tag, e.g. `v1.4.2` -> `v1.5.0` for minor
+    #[arg(
+        long,
+        value_name = "PART",
+        value_parser = PossibleValuesParser::new(["major", "minor", "patch"])
+            .map(|part| part.parse::<TagBump>().unwrap()),
+    )]
+    bump: Option<TagBump>,
+
+    /// Name the tag after the current month, e.g. `2025.03.0`, then `2025.03.1`
+    #[arg(long, conflicts_with = "bump")]
+    calver: bool,
 
     #[arg(short, long, help = "Description of the tag")]
     description: Option<String>,
+
+    /// Skip checking that the lockfile is consistent with the specfile
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Tag the current commit if the lockspec hasn't changed since, instead of refusing
+    #[arg(long)]
+    allow_empty: bool,
+
+    /// Sign the tag with the key configured in ~/.araki/config.toml
+    #[arg(short, long)]
+    sign: bool,
+
+    /// Create a branch for the new commit when HEAD is detached, e.g. after checking out a tag.
+    /// When running interactively, araki offers to create one instead of refusing
+    #[arg(long, value_name = "NAME")]
+    branch: Option<String>,
+
+    /// Move the tag if it already exists. Tags which have been pushed can't be moved
+    #[arg(long)]
+    force: bool,
+
+    /// Author of the commit, as "Name <email>". Defaults to the `author` in the araki config,
+    /// then to git config, then to the user logged in to the backend
+    #[arg(long, value_name = "NAME <EMAIL>")]
+    author: Option<Identity>,
+
+    /// Committer of the commit, as "Name <email>", if it should differ from the author
+    #[arg(long, value_name = "NAME <EMAIL>")]
+    committer: Option<Identity>,
 }
 
-pub fn execute(args: Args) {
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
-
-    let mut index = repo.index().expect("Failed to get index");
-
-    // Add files
-    index
-        .add_path(Path::new("pixi.toml"))
-        .expect("unable to add pixi.toml");
-    index
-        .add_path(Path::new("pixi.lock"))
-        .expect("unable to add pixi.lock");
-    index.write().expect("Failed to write index");
-
-    let tree_oid = index.write_tree().expect("failed to write tree");
-    let tree = repo.find_tree(tree_oid).expect("failed to find tree");
-    let signature = Signature::now("araki", "place@holder.com").expect("failed to get signature");
-    let head = repo.head().expect("Failed to get HEAD");
-    let parent_commit = repo
-        .find_commit(head.target().expect("Failed to get HEAD target OID"))
-        .expect("Failed to find parent commit");
-
-    // Commit change
-    repo.commit(
-        Some("HEAD"),      // Update the HEAD reference
-        &signature,        // Author
-        &signature,        // Committer
-        &args.tag,         // Commit message
-        &tree,             // Tree containing the staged changes
-        &[&parent_commit], // Parent commit(s)
-    )
-    .expect("Failed to create commit");
-
-    // Create tag
-    // Get the OID of the commit to tag (e.g., HEAD)
-    let head = repo.revparse_single("HEAD").expect("unable to find HEAD");
-
-    let tag_message: String;
-    if let Some(ref message) = args.description {
-        tag_message = message.to_string();
+pub async fn execute(args: Args) -> Result<(), ArakiError> {
+    let cwd = current_dir().map_err(ArakiError::io("Could not get the current directory"))?;
+    let workspace = Workspace::discover(cwd)?;
+    if args.delete {
+        return delete(
+            &workspace,
+            &args.tag.unwrap_or_default(),
+            args.remote,
+            args.yes,
+        );
+    }
+    let bump = if args.calver {
+        Some(TagBump::Calver)
     } else {
-        tag_message = format!("araki environment tag: {}", args.tag)
+        args.bump
+    };
+    let name = match (args.tag, bump) {
+        (Some(name), _) => name,
+        (None, Some(bump)) => workspace.next_tag_name(bump)?,
+        (None, None) => {
+            let config = workspace
+                .lockspec()
+                .config()
+                .map_err(ArakiError::LockSpec)?;
+            let bump = config.tag_bump.ok_or(ArakiError::Other(
+                "Pass a tag name, --bump or --calver, or set `tag_bump` in the araki config"
+                    .to_string(),
+            ))?;
+            workspace.next_tag_name(bump)?
+        }
+    };
+    let mut options = TagOptions {
+        description: args.description,
+        no_verify: args.no_verify,
+        author: args.author,
+        committer: args.committer,
+        force: args.force,
+        allow_empty: args.allow_empty,
+        sign: args.sign,
+        branch: args.branch,
+    };
+    match workspace.tag(&name, &options, &print_changes).await {
+        Err(ArakiError::DetachedHead) if prompt::is_interactive() => {
+            let branch = prompt::input(
+                "HEAD is detached, so the new commit wouldn't be on any branch. Name of a branch \
+                    to create for it (leave empty to cancel):",
+            )?;
+            if branch.is_empty() {
+                return Err(ArakiError::DetachedHead);
+            }
+            options.branch = Some(branch);
+            workspace.tag(&name, &options, &print_changes).await?;
+        }
+        result => {
+            result?;
+        }
     }
+    println!("Tagged {name}");
+    Ok(())
+}
 
-    repo.tag(
-        &args.tag,
-        &head,
-        &signature,
-        &tag_message,
-        false, // Set to false for an annotated tag, true for a lightweight tag
-    )
-    .expect("Unable to tag");
+/// Delete a tag after asking for confirmation.
+///
+/// * `workspace`: Workspace to delete the tag from
+/// * `name`: Name of the tag
+/// * `remote`: Also delete the tag from the remote
+/// * `yes`: Don't ask for confirmation
+fn delete(workspace: &Workspace, name: &str, remote: bool, yes: bool) -> Result<(), ArakiError> {
+    let question = if remote {
+        format!("Delete tag '{name}' locally and from the remote? This can't be undone.")
+    } else {
+        format!("Delete tag '{name}'?")
+    };
+    if !yes && !prompt::confirm(&question)? {
+        println!("Tag '{name}' was not deleted.");
+        return Ok(());
+    }
+    workspace.delete_tag(name, remote)?;
+    println!("Deleted tag {name}");
+    Ok(())
+}
+
+/// Print a summary of the package changes since the last tag.
+fn print_changes(last_tag: Option<&str>, changes: &[PackageChange]) {
+    match last_tag {
+        None => println!("First tag, with {}.", diff::locked_packages(changes)),
+        Some(tag) if changes.is_empty() => println!("No package changes since {tag}."),
+        Some(tag) => {
+            println!("Package changes since {tag}:");
+            print!("{}", diff::format_changes(changes));
+        }
+    }
 }
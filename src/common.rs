@@ -1,28 +1,148 @@
 use directories::{ProjectDirs, UserDirs};
-use fs::OpenOptions;
+use filetime::FileTime;
 use git2::build::RepoBuilder;
-use git2::{Cred, FetchOptions, PushOptions, RemoteCallbacks, Repository};
-use std::env::{current_dir, temp_dir};
+use git2::{
+    Cred, Direction, FetchOptions, Index, ObjectType, Oid, PushOptions, RemoteCallbacks,
+    Repository, TreeWalkMode, TreeWalkResult,
+};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env::temp_dir;
 use std::fmt::Display;
 use std::fs;
-use std::io::{Error, ErrorKind, Write};
+use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
-use toml::Table;
 use uuid::Uuid;
 
+use crate::lockspecs::LockSpec;
+use crate::transaction::Transaction;
+
 pub const ARAKI_DIR: &str = ".araki";
 pub const ARAKI_GIT_DIR_NAME: &str = ".araki-git";
 
-/// Get the git directory used by git for lockspec version control
-pub fn get_araki_git_repo() -> Result<Repository, Error> {
-    let cwd = current_dir()?;
-    let araki_git_dir = cwd.join(ARAKI_GIT_DIR_NAME);
-    if !araki_git_dir.exists() {
-        return Err(Error::other(format!(
-            "No araki git dir files found in {araki_git_dir:?}",
-        )));
+/// Get the directory containing the lockspec tracked by an araki git repository.
+///
+/// * `repo`: araki git repository
+pub fn get_araki_workdir(repo: &Repository) -> PathBuf {
+    repo.workdir()
+        .or_else(|| repo.path().parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Read the contents of a file tracked by an araki git repository at a given revision.
+///
+/// * `repo`: araki git repository
+/// * `revision`: Revision to read the file at, e.g. `HEAD` or `refs/tags/v1`
+/// * `path`: Path of the file, relative to the root of the repository
+pub fn read_file_at_revision(
+    repo: &Repository,
+    revision: &str,
+    path: &str,
+) -> Result<String, git2::Error> {
+    let tree = repo.revparse_single(revision)?.peel_to_tree()?;
+    let blob = tree
+        .get_path(Path::new(path))?
+        .to_object(repo)?
+        .peel_to_blob()?;
+    String::from_utf8(blob.content().to_vec())
+        .map_err(|_| git2::Error::from_str(&format!("{path} at {revision} is not valid UTF-8")))
+}
+
+/// How a file tracked for a lockspec differs from the HEAD commit of the araki git repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Display for FileStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            FileStatus::Added => "new file",
+            FileStatus::Modified => "modified",
+            FileStatus::Deleted => "deleted",
+        };
+        write!(f, "{status}")
     }
-    Repository::open(araki_git_dir).map_err(Error::other)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    /// Path of the file, relative to the lockspec directory
+    pub path: PathBuf,
+    pub status: FileStatus,
+}
+
+/// Compare the files tracked for a lockspec with the HEAD commit of the araki git repository.
+///
+/// Returns only the files which differ; an empty list means the lockspec is unchanged.
+///
+/// * `repo`: araki git repository
+/// * `lockspec`: Lockspec tracked by the repository
+pub fn lockspec_status(repo: &Repository, lockspec: &LockSpec) -> Result<Vec<FileChange>, String> {
+    // An unborn HEAD (e.g. a freshly cloned empty repository) has no files
+    let mut head_files: BTreeMap<PathBuf, Oid> = BTreeMap::new();
+    if let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) {
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(ObjectType::Blob) {
+                let name = String::from_utf8_lossy(entry.name_bytes()).to_string();
+                head_files.insert(Path::new(root).join(name), entry.id());
+            }
+            TreeWalkResult::Ok
+        })
+        .map_err(|err| format!("Unable to read the HEAD commit: {err}"))?;
+    }
+
+    let working_files = lockspec.tracked_files()?;
+    let mut changes = vec![];
+    for file in &working_files {
+        let oid = Oid::hash_file(ObjectType::Blob, lockspec.path.join(file))
+            .map_err(|err| format!("Unable to hash {file:?}: {err}"))?;
+        let status = match head_files.get(file) {
+            None => FileStatus::Added,
+            Some(head_oid) if *head_oid != oid => FileStatus::Modified,
+            Some(_) => continue,
+        };
+        changes.push(FileChange {
+            path: file.clone(),
+            status,
+        });
+    }
+    for file in head_files.keys() {
+        if !working_files.contains(file) && lockspec.is_tracked(file)? {
+            changes.push(FileChange {
+                path: file.clone(),
+                status: FileStatus::Deleted,
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changes)
+}
+
+/// Stage the changes to the files tracked for a lockspec in the araki git repository index.
+///
+/// * `repo`: araki git repository
+/// * `lockspec`: Lockspec tracked by the repository
+pub fn stage_lockspec(repo: &Repository, lockspec: &LockSpec) -> Result<Index, String> {
+    let mut index = repo
+        .index()
+        .map_err(|err| format!("Couldn't get the index for the araki repo: {err}"))?;
+    for change in lockspec_status(repo, lockspec)? {
+        match change.status {
+            FileStatus::Deleted => index.remove_path(&change.path),
+            FileStatus::Added | FileStatus::Modified => index.add_path(&change.path),
+        }
+        .map_err(|err| format!("Couldn't add {:?} to the git index: {err}", change.path))?;
+    }
+    index
+        .write()
+        .map_err(|err| format!("Couldn't write to the git index: {err}"))?;
+    Ok(index)
 }
 
 /// Get the path to the araki directory
@@ -52,7 +172,7 @@ pub fn get_araki_cache() -> Result<PathBuf, Error> {
     let dir = get_project_dirs()?;
     let cache = dir.cache_dir();
     if !cache.exists() {
-        println!("araki cache does not exist. Creating it at {cache:?}");
+        info!("araki cache does not exist. Creating it at {cache:?}");
         fs::create_dir_all(cache)?;
     }
     Ok(cache.to_path_buf())
@@ -62,11 +182,9 @@ pub fn get_araki_cache() -> Result<PathBuf, Error> {
 pub fn get_araki_bin_dir() -> Result<PathBuf, String> {
     let dir = get_araki_dir()?.join("bin");
     if !dir.exists() {
-        println!("araki bin dir does not exist. Creating it at {dir:?}");
-        fs::create_dir_all(&dir).map_err(|err| {
-            eprintln!("Could not create araki bin directory at {dir:?}. Error:\n{err}");
-            format!("{err}")
-        })?;
+        info!("araki bin dir does not exist. Creating it at {dir:?}");
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("Could not create araki bin directory at {dir:?}: {err}"))?;
     }
     Ok(dir)
 }
@@ -78,16 +196,71 @@ pub fn get_araki_bin_dir() -> Result<PathBuf, String> {
 /// environment variables.
 ///
 /// Additionally modify `.gitignore` to ignore `.araki-git/` so that it doesn't get treated as
-/// a regular file, as well as `pixi.lock` and `pixi.toml`
+/// a regular file, as well as the specfile and lockfile of the lockspec (e.g. `pixi.toml` and
+/// `pixi.lock`), any files included in the lockspec by the araki configuration and the
+/// directories the install creates (e.g. `.pixi/`)
+///
+/// Every change made to `path` is recorded in `transaction`. Files in `path` which would be
+/// overwritten by a different version from the repo are refused unless `force` is set.
 ///
 /// * `repo`: URL of a git repo to clone
 /// * `path`: Path where the repo should be cloned
-pub fn git_clone(repo: String, path: &Path) -> Result<(), String> {
+/// * `transaction`: Transaction recording the changes made to `path`
+/// * `force`: Overwrite conflicting files in `path`
+pub fn git_clone(
+    repo: String,
+    path: &Path,
+    transaction: &mut Transaction,
+    force: bool,
+) -> Result<(), String> {
     let temp_dir = temp_dir().join(Uuid::new_v4().to_string());
     fs::create_dir_all(&temp_dir).map_err(|err| {
         format!("Unable to clone {repo} to a temporary directory at {temp_dir:?}: {err}")
     })?;
 
+    debug!(repo:% = repo, temp_dir:? = temp_dir; "Cloning to a temporary directory");
+    let result = clone_to_temp_dir(&repo, &temp_dir).and_then(|_| {
+        debug!(from:? = temp_dir, to:? = path, force = force; "Copying the clone");
+        // The temporary clone is discarded afterwards, so its files can be hardlinked
+        transaction
+            .copy_directory_contents(&temp_dir, path, force, CopyStrategy::Hardlink)
+            .map_err(|err| {
+                format!("Error copying the clone repo from {temp_dir:?} to {path:?}: {err}")
+            })
+    });
+    // Ignore any problems that arise during cleanup; just do our best
+    let _ = fs::remove_dir_all(&temp_dir);
+    result?;
+
+    // If need be, write `.araki-git` to `.gitignore`, otherwise git treats it as a regular file
+    // even if GIT_DIR is set
+    let gitignore = path.join(".gitignore");
+    let entry = ".araki-git/";
+    if !std::fs::read_to_string(&gitignore).is_ok_and(|content| content.contains(entry)) {
+        let mut items = vec![entry.to_string()];
+        if let Ok(lockspec) = LockSpec::from_path(path) {
+            items.extend(lockspec.tracked_patterns()?);
+            items.extend(
+                lockspec
+                    .format
+                    .install_dirs()
+                    .iter()
+                    .map(|dir| dir.to_string()),
+            );
+        }
+        debug!(gitignore:? = gitignore, entries:? = items; "Ignoring araki files");
+        let contents: String = items.iter().map(|item| format!("{item}\n")).collect();
+        transaction.append(&gitignore, &contents)?;
+    }
+
+    Ok(())
+}
+
+/// Clone a git repo to a temporary directory, renaming `.git/` to `.araki-git/`.
+///
+/// * `repo`: URL of a git repo to clone
+/// * `temp_dir`: Temporary directory to clone into
+fn clone_to_temp_dir(repo: &str, temp_dir: &Path) -> Result<(), String> {
     let callbacks = generate_remote_callbacks();
     let mut fetch_opts = FetchOptions::new();
     fetch_opts.remote_callbacks(callbacks);
@@ -96,38 +269,16 @@ pub fn git_clone(repo: String, path: &Path) -> Result<(), String> {
     builder.fetch_options(fetch_opts);
 
     let _ = builder
-        .clone(&repo, &temp_dir)
+        .clone(repo, temp_dir)
         .map_err(|err| format!("Failed to clone {repo} to {temp_dir:?}. Reason: {err}"))?;
 
     // Rename `.git` -> `.araki-git`
     fs::rename(temp_dir.join(".git"), temp_dir.join(ARAKI_GIT_DIR_NAME))
-        .map_err(|err| format!("Error modifying the cloned repo: {err}"))?;
-
-    copy_directory_contents(&temp_dir, &path.to_path_buf()).map_err(|err| {
-        format!("Error copying the clone repo from {temp_dir:?} to {path:?}: {err}")
-    })?;
-
-    // If need be, write `.araki-git` to `.gitignore`, otherwise git treats it as a regular file
-    // even if GIT_DIR is set
-    let gitignore = path.join(".gitignore");
-    let entry = ".araki-git/";
-    if !std::fs::read_to_string(&gitignore).is_ok_and(|content| content.contains(entry)) {
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&gitignore)
-            .map_err(|err| format!("Unable to open to {gitignore:?}: {err}"))?;
-
-        for item in [".araki-git/", "pixi.lock", "pixi.toml"] {
-            writeln!(file, "{}", item)
-                .map_err(|err| format!("Unable to write to {gitignore:?}: {err}"))?;
-        }
-    }
-
-    Ok(())
+        .map_err(|err| format!("Error modifying the cloned repo: {err}"))
 }
 
-fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
+/// Build the callbacks used to authenticate with git remotes via ssh-agent.
+pub fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
     let mut callbacks = RemoteCallbacks::new();
 
     // Keep track of whether we've tried to get credentials from ssh-agent.
@@ -147,6 +298,7 @@ fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
             ));
         }
 
+        debug!(username = username, allowed_types:? = allowed_types; "Requesting git credentials");
         if allowed_types.is_ssh_key() {
             tried_agent = true;
             return Cred::ssh_key_from_agent(username);
@@ -160,47 +312,175 @@ fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
     callbacks
 }
 
-pub fn git_push(remote: &str, refs: &[&str]) -> Result<(), git2::Error> {
+/// List the refs of a remote, along with the ids they point to.
+///
+/// Annotated tags are listed twice: once for the tag itself, and once suffixed with `^{}` for the
+/// commit it points to.
+///
+/// * `repo`: araki git repository
+/// * `remote`: Name of the remote, e.g. `origin`
+pub fn list_remote_refs(
+    repo: &Repository,
+    remote: &str,
+) -> Result<Vec<(String, Oid)>, git2::Error> {
+    let mut remote = repo.find_remote(remote)?;
+    let connection =
+        remote.connect_auth(Direction::Fetch, Some(generate_remote_callbacks()), None)?;
+    Ok(connection
+        .list()?
+        .iter()
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect())
+}
+
+/// Fetch the tags of a remote lockspec repository into a new bare repository, without a
+/// working directory. The tags are listed first, like `git ls-remote`, and only the tagged
+/// commits are fetched if the transport supports shallow fetches.
+///
+/// * `url`: URL of the remote lockspec repository
+/// * `path`: Directory where the bare repository is created
+pub fn fetch_tags(url: &str, path: &Path) -> Result<Repository, git2::Error> {
+    let repo = Repository::init_bare(path)?;
+    fetch_tags_into(&repo, url)?;
+    Ok(repo)
+}
+
+/// Fetch the tags of a remote repository into an existing repository. See `fetch_tags`.
+///
+/// * `repo`: Repository to fetch into
+/// * `url`: URL of the remote repository
+fn fetch_tags_into(repo: &Repository, url: &str) -> Result<(), git2::Error> {
+    let mut remote = repo.remote_anonymous(url)?;
+    let connection =
+        remote.connect_auth(Direction::Fetch, Some(generate_remote_callbacks()), None)?;
+    let refspecs: Vec<String> = connection
+        .list()?
+        .iter()
+        .map(|head| head.name())
+        .filter(|name| name.starts_with("refs/tags/") && !name.ends_with("^{}"))
+        .map(|name| format!("+{name}:{name}"))
+        .collect();
+    drop(connection);
+    if refspecs.is_empty() {
+        return Ok(());
+    }
+
+    // A depth of 0 fetches the whole history
+    for depth in [1, 0] {
+        let mut fetch_opts = FetchOptions::new();
+        fetch_opts
+            .remote_callbacks(generate_remote_callbacks())
+            .depth(depth);
+        match remote.fetch(&refspecs, Some(&mut fetch_opts), None) {
+            Ok(()) => break,
+            Err(err) if depth > 0 => {
+                debug!(url = url; "Shallow fetch failed, fetching the whole history: {err}")
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Push refs of an araki git repository to a remote.
+///
+/// * `repo`: araki git repository
+/// * `remote`: Name of the remote, e.g. `origin`
+/// * `refs`: Refspecs to push
+pub fn git_push(repo: &Repository, remote: &str, refs: &[&str]) -> Result<(), git2::Error> {
     let callbacks = generate_remote_callbacks();
 
     let mut push_options = PushOptions::new();
     push_options.remote_callbacks(callbacks);
-    let repo =
-        get_araki_git_repo().map_err(|err| git2::Error::from_str(format!("{err}").as_str()))?;
     let mut origin = repo.find_remote(remote)?;
+    debug!(remote = remote, refs:? = refs; "Pushing");
     origin.push(refs, Some(&mut push_options))?;
     Ok(())
 }
 
+/// How file contents are transferred when copying a filesystem object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyStrategy {
+    /// Always copy the data.
+    Copy,
+    /// Clone files with a copy-on-write reflink where the filesystem supports it (btrfs, XFS,
+    /// APFS, ...), which is nearly free even for large files. Falls back to copying.
+    #[default]
+    Reflink,
+    /// Hardlink files where possible, falling back to a reflink and then a copy. The source and
+    /// destination share the same data afterwards, so only use this when the source is about to
+    /// be discarded.
+    Hardlink,
+}
+
+/// An error encountered while copying a specific filesystem object.
+#[derive(Debug)]
+pub struct CopyError {
+    /// Path the failed operation was applied to
+    pub path: PathBuf,
+    /// What was being done, e.g. "read", "create", "set permissions on"
+    pub operation: &'static str,
+    pub source: Error,
+}
+
+impl Display for CopyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to {} {:?}: {}",
+            self.operation, self.path, self.source
+        )
+    }
+}
+
+impl std::error::Error for CopyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Attach the path and operation to an io error.
+///
+/// * `operation`: What was being done, e.g. "read"
+/// * `path`: Path the operation was applied to
+fn copy_err(operation: &'static str, path: &Path) -> impl FnOnce(Error) -> CopyError {
+    move |source| CopyError {
+        path: path.to_path_buf(),
+        operation,
+        source,
+    }
+}
+
 /// Copy the contents of a directory to another directory.
 ///
+/// If a problem is encountered, the objects copied so far are removed.
+///
 /// * `from`: Directory containing some filesystem objects
 /// * `to`: Directory where they should be copied
-pub fn copy_directory_contents(from: &PathBuf, to: &PathBuf) -> std::io::Result<()> {
+/// * `strategy`: How file contents should be transferred
+pub fn copy_directory_contents(
+    from: &Path,
+    to: &Path,
+    strategy: CopyStrategy,
+) -> Result<(), CopyError> {
     // Keep track of what has been copied so we can roll back if necessary
     let mut copied: Vec<PathBuf> = vec![];
-    for item in fs::read_dir(from)? {
-        let entry = match item {
-            Ok(e) => e,
-            Err(ref err) => {
-                // Ignore any problems that arise during cleanup; just do our best
-                let _ = remove_files(copied);
-                return Err(Error::other(format!(
-                    "Error reading {item:?}.\nReason: {err}"
-                )));
+    let result = fs::read_dir(from)
+        .map_err(copy_err("read", from))
+        .and_then(|entries| {
+            for entry in entries {
+                let entry = entry.map_err(copy_err("read", from))?;
+                let fsobj = to.join(entry.file_name());
+                copy_fs_obj(&entry.path(), &fsobj, strategy)?;
+                copied.push(fsobj);
             }
-        };
-        let fsobj = to.join(entry.file_name());
-        if copy_fs_obj(&entry.path(), &fsobj).is_err() {
-            // Ignore any problems that arise during cleanup; just do our best
-            let _ = remove_files(copied);
-            return Err(Error::other(format!(
-                "Unknown issue copying {from:?} to {to:?}."
-            )));
-        }
-        copied.push(fsobj);
+            Ok(())
+        });
+    if result.is_err() {
+        // Ignore any problems that arise during cleanup; just do our best
+        let _ = remove_files(copied);
     }
-    Ok(())
+    result
 }
 
 /// Remove all the files or directories in the given vector if they exist.
@@ -234,159 +514,142 @@ pub fn remove_files(files: Vec<PathBuf>) -> std::io::Result<()> {
 ///
 /// * `from`: Path to be copied
 /// * `to`: Destination of the copied directory
-pub fn copy_directory(from: &PathBuf, to: &PathBuf) -> std::io::Result<()> {
+/// * `strategy`: How file contents should be transferred
+pub fn copy_directory(from: &Path, to: &Path, strategy: CopyStrategy) -> Result<(), CopyError> {
     if !from.is_dir() {
-        return Err(Error::new(
-            ErrorKind::NotADirectory,
-            format!("{from:?} is not a directory"),
-        ));
+        return Err(CopyError {
+            path: from.to_path_buf(),
+            operation: "copy",
+            source: Error::new(ErrorKind::NotADirectory, "not a directory"),
+        });
     }
 
     if to.exists() {
-        return Err(Error::new(
-            ErrorKind::AlreadyExists,
-            format!("{to:?} already exists"),
-        ));
+        return Err(CopyError {
+            path: to.to_path_buf(),
+            operation: "copy to",
+            source: Error::new(ErrorKind::AlreadyExists, "already exists"),
+        });
     }
 
-    fs::create_dir_all(to)?;
-    for item in fs::read_dir(from)? {
-        let entry = match item {
-            Ok(e) => e,
-            Err(ref err) => {
-                fs::remove_dir_all(to)?;
-                return Err(Error::other(format!(
-                    "Error reading {item:?}.\nReason: {err}"
-                )));
-            }
-        };
-        if copy_fs_obj(from, &to.join(entry.file_name())).is_err() {
-            // Clean up the new directory
-            if to.is_dir() {
-                fs::remove_dir_all(to)?;
-            }
-            return Err(Error::other(format!(
-                "Unknown issue copying {from:?} to {to:?}."
-            )));
-        }
+    let result = copy_fs_obj(from, to, strategy);
+    if result.is_err() && to.is_dir() {
+        // Clean up the new directory
+        let _ = fs::remove_dir_all(to);
     }
-    Ok(())
+    result
 }
 
 /// Copy a filesystem object from one place to another.
 ///
-/// Directories are copied recursively.
+/// Directories are copied recursively, symlinks are recreated rather than followed, and
+/// permissions and modification times are preserved. Existing files at the destination are
+/// replaced.
 ///
 /// * `from`: Path to be copied
 /// * `to`: Destination of the copied object
-fn copy_fs_obj(from: &PathBuf, to: &Path) -> std::io::Result<()> {
-    if from.is_dir() {
-        fs::create_dir_all(to)?;
-        copy_directory_contents(from, &to.to_path_buf())?;
+/// * `strategy`: How file contents should be transferred
+pub fn copy_fs_obj(from: &Path, to: &Path, strategy: CopyStrategy) -> Result<(), CopyError> {
+    let metadata = fs::symlink_metadata(from).map_err(copy_err("read", from))?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_dir() {
+        if !to.is_dir() {
+            fs::create_dir(to).map_err(copy_err("create", to))?;
+        }
+        copy_directory_contents(from, to, strategy)?;
     } else {
-        let _ = fs::copy(from, to)
-            .map_err(|err| Error::other(format!("Error copying {from:?} to {to:?}: {err}")))?;
+        // Symlinks, reflinks and hardlinks can't be created over an existing file
+        match fs::symlink_metadata(to) {
+            Ok(existing) if existing.is_dir() => {
+                return Err(CopyError {
+                    path: to.to_path_buf(),
+                    operation: "replace",
+                    source: Error::new(ErrorKind::IsADirectory, "is a directory"),
+                });
+            }
+            Ok(_) => fs::remove_file(to).map_err(copy_err("replace", to))?,
+            Err(_) => (),
+        }
+
+        if file_type.is_symlink() {
+            let target = fs::read_link(from).map_err(copy_err("read link", from))?;
+            create_symlink(&target, from, to).map_err(copy_err("create symlink", to))?;
+        } else if copy_file_data(from, to, strategy).map_err(copy_err("copy to", to))?
+            == CopyStrategy::Hardlink
+        {
+            // Hardlinks share their metadata with the source already
+            return Ok(());
+        }
     }
-    Ok(())
+    apply_metadata(&metadata, to)
 }
 
-#[derive(Debug, Clone)]
-pub struct LockSpec {
-    pub path: PathBuf,
+/// Create a symlink with the same target as an existing one.
+///
+/// * `target`: Target of the existing symlink
+/// * `from`: Existing symlink
+/// * `to`: Path of the new symlink
+#[cfg(unix)]
+fn create_symlink(target: &Path, _from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, to)
 }
 
-impl Display for LockSpec {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "lockspec: {:?}", self.path)
+/// Create a symlink with the same target as an existing one.
+///
+/// * `target`: Target of the existing symlink
+/// * `from`: Existing symlink
+/// * `to`: Path of the new symlink
+#[cfg(windows)]
+fn create_symlink(target: &Path, from: &Path, to: &Path) -> std::io::Result<()> {
+    // Windows has distinct symlinks to files and directories; a dangling one links to a file
+    if fs::metadata(from).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
     }
 }
 
-impl LockSpec {
-    pub fn specfile(&self) -> PathBuf {
-        self.path.join("pixi.toml")
-    }
-
-    pub fn lockfile(&self) -> PathBuf {
-        self.path.join("pixi.lock")
-    }
-
-    /// Construct a LockSpec from the given path.
-    ///
-    /// * `path`: Path to a directory containing a pixi.lock and a pixi.toml
-    pub fn from_path<T>(path: T) -> Result<LockSpec, String>
-    where
-        T: AsRef<Path> + std::fmt::Debug,
-    {
-        let ls = LockSpec {
-            path: path.as_ref().to_path_buf(),
-        };
-
-        if ls.files_exist() {
-            Ok(ls)
-        } else {
-            Err(format!("No lockspec files found in {:?}", path))
-        }
+/// Transfer the contents of a regular file, returning the strategy which ended up being used.
+///
+/// * `from`: File to be copied
+/// * `to`: Destination, which must not exist
+/// * `strategy`: Preferred way of transferring the contents
+fn copy_file_data(from: &Path, to: &Path, strategy: CopyStrategy) -> std::io::Result<CopyStrategy> {
+    if strategy == CopyStrategy::Hardlink && fs::hard_link(from, to).is_ok() {
+        return Ok(CopyStrategy::Hardlink);
     }
-
-    /// Check whether a lockspec (lockfile+specfile) exist in self.path.
-    pub fn files_exist(&self) -> bool {
-        self.lockfile().exists() && self.specfile().exists()
+    if strategy != CopyStrategy::Copy && reflink_copy::reflink(from, to).is_ok() {
+        return Ok(CopyStrategy::Reflink);
     }
+    fs::copy(from, to)?;
+    Ok(CopyStrategy::Copy)
+}
 
-    /// Ensure that the araki metadata containing the lockspec name is written to the specfile.
-    ///
-    /// * `lockspec_name`: Lockspec name to write to the file
-    pub fn ensure_araki_metadata(&self, lockspec_name: &str) -> Result<(), String> {
-        let specfile = self.specfile();
-
-        let file = std::fs::read_to_string(&specfile)
-            .map_err(|_| format!("Unable to read file {specfile:?}"))?;
-
-        let mut toml_data: Table = file
-            .parse()
-            .map_err(|err| format!("Unable to parse {specfile:?} as valid toml.\nReason: {err}"))?;
-
-        if toml_data.get("araki").is_none() {
-            let mut araki_table = Table::new();
-            araki_table.insert("lockspec_name".to_string(), lockspec_name.into());
-            toml_data.insert("araki".to_string(), toml::Value::Table(araki_table));
-
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&specfile)
-                .map_err(|err| {
-                    format!(
-                        "Unable to open araki config at {specfile:?} for writing.\nReason: {err}"
-                    )
-                })?;
-            file.write_all(toml_data.to_string().as_bytes())
-                .map_err(|err| {
-                    format!("Unable to write araki config to {specfile:?}.\nReason: {err}")
-                })?;
-        }
-        Ok(())
-    }
+/// Apply the permissions and modification time of a filesystem object to its copy.
+///
+/// * `from`: Original filesystem object
+/// * `to`: Copy of the object
+pub fn copy_metadata(from: &Path, to: &Path) -> Result<(), CopyError> {
+    let metadata = fs::symlink_metadata(from).map_err(copy_err("read", from))?;
+    apply_metadata(&metadata, to)
+}
 
-    /// Remove the lockfile, specfile, and .araki-git/ directory from the given path.
-    /// No error is thrown if these files don't exist.
-    pub fn remove_files(&self) -> Result<(), String> {
-        match fs::remove_file(self.specfile()) {
-            Ok(_) => (),
-            Err(e) if e.kind() == ErrorKind::NotFound => (),
-            Err(e) => return Err(e.to_string()),
-        }
-        match fs::remove_file(self.lockfile()) {
-            Ok(_) => (),
-            Err(e) if e.kind() == ErrorKind::NotFound => (),
-            Err(e) => return Err(e.to_string()),
-        }
-        match fs::remove_dir_all(self.path.join(".araki-git")) {
-            Ok(_) => (),
-            Err(e) if e.kind() == ErrorKind::NotFound => (),
-            Err(e) => return Err(e.to_string()),
-        }
-        Ok(())
+/// Apply permissions and a modification time to a filesystem object.
+///
+/// * `metadata`: Metadata of the original object, not following symlinks
+/// * `to`: Object to apply the metadata to
+fn apply_metadata(metadata: &fs::Metadata, to: &Path) -> Result<(), CopyError> {
+    let mtime = FileTime::from_last_modification_time(metadata);
+    if metadata.file_type().is_symlink() {
+        // Symlink permissions aren't meaningful on unix, only their timestamps are
+        return filetime::set_symlink_file_times(
+            to,
+            FileTime::from_last_access_time(metadata),
+            mtime,
+        )
+        .map_err(copy_err("set modification time of", to));
     }
+    fs::set_permissions(to, metadata.permissions()).map_err(copy_err("set permissions of", to))?;
+    filetime::set_file_mtime(to, mtime).map_err(copy_err("set modification time of", to))
 }
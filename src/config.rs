@@ -0,0 +1,124 @@
+use directories::UserDirs;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::common::get_araki_dir;
+use crate::error::ArakiError;
+use crate::signing::SignatureFormat;
+use crate::version::TagBump;
+
+const USER_CONFIG_FILE_NAME: &str = "config.toml";
+
+/// araki configuration for a lockspec.
+///
+/// This is read from the `[araki]` table of `pixi.toml`, the `[tool.araki]` table of
+/// `pyproject.toml`, or the `araki` key of `environment.yml`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct ArakiConfig {
+    /// Name of the lockspec
+    pub lockspec_name: Option<String>,
+
+    /// Extra files to track alongside the specfile and lockfile, e.g. activation scripts.
+    /// Glob patterns, relative to the lockspec directory
+    pub include: Vec<String>,
+
+    /// Author of the commits and tags araki creates, as `Name <email>`
+    pub author: Option<String>,
+
+    /// Committer of the commits and tags araki creates, as `Name <email>`, if it should differ
+    /// from the author, e.g. in automation
+    pub committer: Option<String>,
+
+    /// Regular expression which the names of new tags must match, e.g. `^v\d+\.\d+\.\d+$`
+    pub tag_pattern: Option<String>,
+
+    /// How to name new tags when `araki tag` is run without a name: `major`, `minor`, `patch`
+    /// or `calver`
+    pub tag_bump: Option<TagBump>,
+}
+
+/// Settings of the user running araki, read from `~/.araki/config.toml`.
+///
+/// Unlike `ArakiConfig`, these don't travel with the lockspec, so they can hold trust
+/// decisions, e.g. which signers to trust.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct UserConfig {
+    pub signing: SigningConfig,
+}
+
+/// How tags are signed and verified.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// Format of the signatures araki creates
+    pub format: SignatureFormat,
+
+    /// Key to sign tags with: the path of an SSH private or public key (the private key may
+    /// live in ssh-agent), or a GPG key id
+    pub key: Option<String>,
+
+    /// Sign every tag, as if `--sign` was passed
+    pub sign_tags: bool,
+
+    /// SSH allowed signers file trusted for verification, in the format of `ssh-keygen`
+    /// (`<email> <public key>` per line)
+    pub allowed_signers: Option<PathBuf>,
+
+    /// Fingerprints of the GPG keys trusted for verification
+    pub trusted_keys: Vec<String>,
+
+    /// Refuse to check out or clone tags without a trusted signature
+    pub require_signed_tags: bool,
+}
+
+impl SigningConfig {
+    /// Whether any signer is trusted, i.e. whether signatures can be verified at all.
+    pub fn has_trusted_signers(&self) -> bool {
+        self.allowed_signers.is_some() || !self.trusted_keys.is_empty()
+    }
+}
+
+impl UserConfig {
+    /// Get the path to the user configuration file.
+    pub fn path() -> Result<PathBuf, ArakiError> {
+        Ok(get_araki_dir()?.join(USER_CONFIG_FILE_NAME))
+    }
+
+    /// Load the user configuration, or the default configuration if there is none.
+    pub fn load() -> Result<UserConfig, ArakiError> {
+        Self::load_from(&Self::path()?)
+    }
+
+    /// Load the user configuration from a file, or the default configuration if the file
+    /// doesn't exist.
+    ///
+    /// * `path`: User configuration file
+    pub fn load_from(path: &Path) -> Result<UserConfig, ArakiError> {
+        if !path.exists() {
+            return Ok(UserConfig::default());
+        }
+        let contents = fs::read_to_string(path).map_err(ArakiError::io(format!(
+            "Unable to read the araki config {path:?}"
+        )))?;
+        let mut config: UserConfig = toml::from_str(&contents)
+            .map_err(|err| ArakiError::Other(format!("Invalid araki config {path:?}: {err}")))?;
+        config.signing.allowed_signers = config
+            .signing
+            .allowed_signers
+            .map(|path| expand_home(&path));
+        Ok(config)
+    }
+}
+
+/// Expand a leading `~` in a path to the home directory of the user.
+///
+/// * `path`: Path which may start with `~`
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), UserDirs::new()) {
+        (Ok(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,424 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use crate::lockspecs::{Dependency, Package};
+
+/// How a package changed between two versions of a lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+    /// The version string changed, but neither version is newer, e.g. a rebuild
+    Changed,
+}
+
+/// A package which differs between two versions of a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageChange {
+    /// Environment the package belongs to, e.g. `default`
+    pub environment: String,
+    /// Platform the package was locked for, e.g. `linux-64`
+    pub platform: String,
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Version before the change; `None` if the package was added
+    pub old_version: Option<String>,
+    /// Version after the change; `None` if the package was removed
+    pub new_version: Option<String>,
+}
+
+/// A dependency whose declaration differs between two versions of a specfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyChange {
+    /// Environment the dependency belongs to, e.g. `default`
+    pub environment: String,
+    /// Platform the dependency applies to, or `None` if it applies to every locked platform
+    pub platform: Option<String>,
+    pub name: String,
+    /// `Added`, `Removed` or `Changed`; version specs aren't ordered
+    pub kind: ChangeKind,
+    /// Version spec before the change; `None` if the dependency was added
+    pub old_spec: Option<String>,
+    /// Version spec after the change; `None` if the dependency was removed
+    pub new_spec: Option<String>,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Upgraded => "upgraded",
+            ChangeKind::Downgraded => "downgraded",
+            ChangeKind::Changed => "changed",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+impl Display for PackageChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let old = self.old_version.as_deref().unwrap_or_default();
+        let new = self.new_version.as_deref().unwrap_or_default();
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {} {new}", self.name),
+            ChangeKind::Removed => write!(f, "- {} {old}", self.name),
+            ChangeKind::Upgraded => write!(f, "↑ {} {old} -> {new}", self.name),
+            ChangeKind::Downgraded => write!(f, "↓ {} {old} -> {new}", self.name),
+            ChangeKind::Changed => write!(f, "~ {} {old} -> {new}", self.name),
+        }
+    }
+}
+
+impl Display for DependencyChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // An empty spec leaves the dependency unconstrained
+        let spec = |spec: &Option<String>| match spec.as_deref() {
+            None | Some("") => "*".to_string(),
+            Some(spec) => spec.to_string(),
+        };
+        let (old, new) = (spec(&self.old_spec), spec(&self.new_spec));
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {} {new}", self.name),
+            ChangeKind::Removed => write!(f, "- {} {old}", self.name),
+            _ => write!(f, "~ {} {old} -> {new}", self.name),
+        }
+    }
+}
+
+/// Split a version into runs of digits and runs of other alphanumeric characters, e.g.
+/// `1.10rc2` into `1`, `10`, `rc`, `2`.
+fn version_tokens(version: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let bytes = version.as_bytes();
+    for (i, c) in version.char_indices() {
+        let boundary = !c.is_ascii_alphanumeric()
+            || start
+                .is_some_and(|start: usize| bytes[start].is_ascii_digit() != c.is_ascii_digit());
+        if boundary && let Some(start) = start.take() {
+            tokens.push(&version[start..i]);
+        }
+        if c.is_ascii_alphanumeric() && start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&version[start..]);
+    }
+    tokens
+}
+
+/// Compare two package versions.
+///
+/// Numeric components are compared as numbers and other components alphabetically. A version
+/// followed by a pre-release tag, e.g. `2.0rc1`, is older than the version itself, but a
+/// version followed by `post` is newer.
+///
+/// * `a`: First version
+/// * `b`: Second version
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a_tokens, b_tokens) = (version_tokens(a), version_tokens(b));
+    for i in 0..a_tokens.len().max(b_tokens.len()) {
+        let ordering = match (a_tokens.get(i), b_tokens.get(i)) {
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            },
+            (Some(a), None) => extra_token_ordering(a),
+            (None, Some(b)) => extra_token_ordering(b).reverse(),
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// How a version with an extra token compares to the same version without it.
+fn extra_token_ordering(token: &str) -> Ordering {
+    if token.parse::<u64>().is_ok() || token.eq_ignore_ascii_case("post") {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    }
+}
+
+/// Compare the packages of two versions of a lockfile.
+///
+/// Returns the packages which were added, removed or changed version, sorted by environment,
+/// platform and name.
+///
+/// * `old`: Packages of the older lockfile
+/// * `new`: Packages of the newer lockfile
+pub fn diff_packages(old: &[Package], new: &[Package]) -> Vec<PackageChange> {
+    let index = |packages: &[Package]| -> BTreeMap<(String, String, String), String> {
+        packages
+            .iter()
+            .map(|pkg| {
+                (
+                    (
+                        pkg.environment.clone(),
+                        pkg.platform.clone(),
+                        pkg.name.clone(),
+                    ),
+                    pkg.version.clone(),
+                )
+            })
+            .collect()
+    };
+    let (mut old, new) = (index(old), index(new));
+
+    let mut changes = vec![];
+    for ((environment, platform, name), new_version) in new {
+        let old_version = old.remove(&(environment.clone(), platform.clone(), name.clone()));
+        let kind = match &old_version {
+            None => ChangeKind::Added,
+            Some(old_version) if *old_version == new_version => continue,
+            Some(old_version) => match compare_versions(old_version, &new_version) {
+                Ordering::Less => ChangeKind::Upgraded,
+                Ordering::Greater => ChangeKind::Downgraded,
+                Ordering::Equal => ChangeKind::Changed,
+            },
+        };
+        changes.push(PackageChange {
+            environment,
+            platform,
+            name,
+            kind,
+            old_version,
+            new_version: Some(new_version),
+        });
+    }
+    for ((environment, platform, name), old_version) in old {
+        changes.push(PackageChange {
+            environment,
+            platform,
+            name,
+            kind: ChangeKind::Removed,
+            old_version: Some(old_version),
+            new_version: None,
+        });
+    }
+    changes.sort_by(|a, b| {
+        (&a.environment, &a.platform, &a.name).cmp(&(&b.environment, &b.platform, &b.name))
+    });
+    changes
+}
+
+/// Compare the dependencies declared in two versions of a specfile.
+///
+/// Returns the dependencies which were added, removed or given another version spec, sorted by
+/// environment, platform and name.
+///
+/// * `old`: Dependencies of the older specfile
+/// * `new`: Dependencies of the newer specfile
+pub fn diff_dependencies(old: &[Dependency], new: &[Dependency]) -> Vec<DependencyChange> {
+    type Key = (String, Option<String>, String);
+    let index = |dependencies: &[Dependency]| -> BTreeMap<Key, String> {
+        dependencies
+            .iter()
+            .map(|dep| {
+                (
+                    (
+                        dep.environment.clone(),
+                        dep.platform.clone(),
+                        dep.name.clone(),
+                    ),
+                    dep.spec.clone(),
+                )
+            })
+            .collect()
+    };
+    let (old, new) = (index(old), index(new));
+
+    let keys: BTreeSet<&Key> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let (old_spec, new_spec) = (old.get(key).cloned(), new.get(key).cloned());
+            let kind = match (&old_spec, &new_spec) {
+                (None, _) => ChangeKind::Added,
+                (_, None) => ChangeKind::Removed,
+                (old_spec, new_spec) if old_spec == new_spec => return None,
+                _ => ChangeKind::Changed,
+            };
+            let (environment, platform, name) = key.clone();
+            Some(DependencyChange {
+                environment,
+                platform,
+                name,
+                kind,
+                old_spec,
+                new_spec,
+            })
+        })
+        .collect()
+}
+
+/// Heading under which the changes of an environment and platform are listed. The environment
+/// is omitted if it is `default`.
+///
+/// * `environment`: Environment of the changes
+/// * `platform`: Platform of the changes, or `None` for changes applying to every platform
+pub fn heading(environment: &str, platform: Option<&str>) -> String {
+    let platform = platform.unwrap_or("all platforms");
+    if environment == "default" {
+        format!("{platform}:")
+    } else {
+        format!("{environment} ({platform}):")
+    }
+}
+
+/// Summarize package changes in a single line, e.g. `1 added, 2 upgraded`. Packages which
+/// changed the same way on several platforms are counted once.
+///
+/// * `changes`: Package changes
+pub fn headline(changes: &[PackageChange]) -> String {
+    let mut counts: BTreeMap<ChangeKind, BTreeSet<(&str, &str)>> = BTreeMap::new();
+    for change in changes {
+        counts
+            .entry(change.kind)
+            .or_default()
+            .insert((&change.environment, &change.name));
+    }
+    if counts.is_empty() {
+        return "No package changes".to_string();
+    }
+    counts
+        .iter()
+        .map(|(kind, packages)| format!("{} {kind}", packages.len()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Describe how many packages are locked, e.g. `3 locked packages`. A package locked in
+/// several environments or for several platforms is counted once.
+///
+/// * `changes`: Changes adding every locked package, as returned by `diff_packages` for a
+///   first lockfile
+pub fn locked_packages(changes: &[PackageChange]) -> String {
+    let count = changes
+        .iter()
+        .map(|change| change.name.as_str())
+        .collect::<BTreeSet<_>>()
+        .len();
+    format!(
+        "{count} locked package{}",
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Format package changes as one line per package, grouped under a `heading` per environment
+/// and platform.
+///
+/// * `changes`: Package changes, sorted as returned by `diff_packages`
+pub fn format_changes(changes: &[PackageChange]) -> String {
+    let mut summary = String::new();
+    let mut group = None;
+    for change in changes {
+        let current = (change.environment.as_str(), change.platform.as_str());
+        if group != Some(current) {
+            group = Some(current);
+            summary.push_str(&heading(&change.environment, Some(&change.platform)));
+            summary.push('\n');
+        }
+        summary.push_str(&format!("  {change}\n"));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(platform: &str, name: &str, version: &str) -> Package {
+        Package {
+            environment: "default".to_string(),
+            platform: platform.to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("3.9.1", "3.13.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0", "2.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0rc1", "2.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.post1", "2.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2.3", "1.2.3"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_diff_packages() {
+        let old = [
+            package("linux-64", "python", "3.12.1"),
+            package("linux-64", "numpy", "2.3.0"),
+            package("linux-64", "six", "1.16.0"),
+            package("osx-arm64", "python", "3.12.1"),
+        ];
+        let new = [
+            package("linux-64", "python", "3.13.0"),
+            package("linux-64", "numpy", "2.2.0"),
+            package("linux-64", "requests", "2.32.3"),
+            package("osx-arm64", "python", "3.12.1"),
+        ];
+        let changes = diff_packages(&old, &new);
+        assert_eq!(
+            format_changes(&changes),
+            "linux-64:\n  ↓ numpy 2.3.0 -> 2.2.0\n  ↑ python 3.12.1 -> 3.13.0\n  \
+                + requests 2.32.3\n  - six 1.16.0\n"
+        );
+        assert_eq!(
+            headline(&changes),
+            "1 added, 1 removed, 1 upgraded, 1 downgraded"
+        );
+        assert!(diff_packages(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_dependencies() {
+        let dependency = |platform: Option<&str>, name: &str, spec: &str| Dependency {
+            environment: "default".to_string(),
+            platform: platform.map(str::to_string),
+            name: name.to_string(),
+            spec: spec.to_string(),
+        };
+        let old = [
+            dependency(None, "python", "3.12.*"),
+            dependency(None, "six", ""),
+            dependency(Some("linux-64"), "numpy", ">=2"),
+        ];
+        let new = [
+            dependency(None, "python", "3.13.*"),
+            dependency(None, "requests", ">=2.32"),
+            dependency(Some("linux-64"), "numpy", ">=2"),
+        ];
+        let changes: Vec<String> = diff_dependencies(&old, &new)
+            .iter()
+            .map(|change| {
+                format!(
+                    "{} {change}",
+                    heading(&change.environment, change.platform.as_deref())
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "all platforms: ~ python 3.12.* -> 3.13.*",
+                "all platforms: + requests >=2.32",
+                "all platforms: - six *",
+            ]
+        );
+        assert!(diff_dependencies(&new, &new).is_empty());
+    }
+}
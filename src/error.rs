@@ -0,0 +1,243 @@
+use std::fmt::Display;
+use std::io;
+use std::path::PathBuf;
+
+use crate::backends::BackendError;
+use crate::common::{CopyError, FileChange};
+
+/// An error returned by any araki operation.
+#[derive(Debug)]
+pub enum ArakiError {
+    /// The directory has no `.araki-git` repository
+    NotManaged(PathBuf),
+    /// The directory already has an `.araki-git` repository
+    AlreadyManaged(PathBuf),
+    /// No environment is registered with the given name, and no directory has it as path
+    UnknownEnv(String),
+    /// Several registered environments have the given name; contains their paths
+    AmbiguousEnv {
+        name: String,
+        paths: Vec<PathBuf>,
+    },
+    /// No supported lockspec was found in the directory
+    NoLockSpec(PathBuf),
+    /// A lockspec already exists in the directory where another was about to be cloned
+    LockSpecExists(PathBuf),
+    /// The lockspec has uncommitted changes which the operation would overwrite; contains the
+    /// changed files
+    DirtyWorkspace(Vec<FileChange>),
+    /// A stash has already been saved and not popped
+    StashExists,
+    /// No stash has been saved
+    NoStash,
+    /// The lockfile is inconsistent with the specfile; contains every problem found
+    Inconsistent(Vec<String>),
+    /// A lockspec couldn't be read, parsed or installed
+    LockSpec(String),
+    /// No tag, branch or commit matches the given revision
+    RevisionNotFound(String),
+    /// A tag with the given name already exists
+    TagExists(String),
+    /// A tag with the given name has already been pushed to the remote, so it can't be moved
+    TagOnRemote(String),
+    /// The tag is checked out, so it can't be deleted
+    TagCheckedOut(String),
+    /// The tag has no trusted signature, and the user requires one
+    UntrustedTag {
+        revision: String,
+        reason: String,
+    },
+    /// A tag couldn't be signed, or its signature couldn't be checked
+    Signing(String),
+    /// HEAD is detached, so a new commit wouldn't be on any branch
+    DetachedHead,
+    /// The lockspec hasn't changed since the last commit, so there is nothing new to tag
+    NothingToTag,
+    /// The name isn't allowed as a tag name
+    InvalidTagName {
+        name: String,
+        reason: String,
+    },
+    /// A lockspec repository name or URL couldn't be parsed
+    InvalidRemote {
+        remote: String,
+        reason: String,
+    },
+    /// A git operation failed
+    Git {
+        context: String,
+        source: git2::Error,
+    },
+    /// A filesystem or process operation failed
+    Io {
+        context: String,
+        source: io::Error,
+    },
+    /// A request to the backend failed
+    Backend {
+        context: String,
+        source: BackendError,
+    },
+    /// A filesystem object couldn't be copied
+    Copy(CopyError),
+    /// An operation failed, and the changes it had made couldn't all be undone
+    Rollback {
+        error: Box<ArakiError>,
+        rollback: String,
+    },
+    Other(String),
+}
+
+impl ArakiError {
+    /// Wrap a git error with a description of what was being done, for use with `map_err`.
+    ///
+    /// * `context`: What was being done, e.g. "Unable to push to remote"
+    pub fn git(context: impl Into<String>) -> impl FnOnce(git2::Error) -> ArakiError {
+        move |source| ArakiError::Git {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Wrap an io error with a description of what was being done, for use with `map_err`.
+    ///
+    /// * `context`: What was being done, e.g. "Could not get the current directory"
+    pub fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> ArakiError {
+        move |source| ArakiError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Wrap a backend error with a description of what was being done, for use with `map_err`.
+    ///
+    /// * `context`: What was being done, e.g. "Unable to login"
+    pub fn backend(context: impl Into<String>) -> impl FnOnce(BackendError) -> ArakiError {
+        move |source| ArakiError::Backend {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl Display for ArakiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArakiError::NotManaged(path) => write!(
+                f,
+                "{path:?} is not managed by araki. Run `araki init` or `araki clone` first."
+            ),
+            ArakiError::AlreadyManaged(path) => write!(f, "{path:?} is already managed by araki."),
+            ArakiError::UnknownEnv(name) => write!(
+                f,
+                "No environment named '{name}'. Run `araki envs ls` to list the environments."
+            ),
+            ArakiError::AmbiguousEnv { name, paths } => write!(
+                f,
+                "Several environments are named '{name}': {}. Pass the path of one of them \
+                    instead.",
+                paths
+                    .iter()
+                    .map(|path| format!("{path:?}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ArakiError::NoLockSpec(path) => write!(f, "No lockspec found at {path:?}"),
+            ArakiError::LockSpecExists(path) => write!(
+                f,
+                "A lockspec already exists at {path:?}. Use --force to overwrite it."
+            ),
+            ArakiError::DirtyWorkspace(changes) => write!(
+                f,
+                "The lockspec has {} uncommitted change(s). Tag them, pass --stash to save them \
+                    for `araki stash pop`, or pass --force to discard them.",
+                changes.len()
+            ),
+            ArakiError::StashExists => write!(
+                f,
+                "A stash has already been saved. Restore it with `araki stash pop` first."
+            ),
+            ArakiError::NoStash => write!(f, "No stash has been saved."),
+            ArakiError::Inconsistent(problems) => write!(
+                f,
+                "Lockspec check failed with {} problem(s). Run `pixi lock` (or the equivalent \
+                    for your lockspec format) to update the lockfile.",
+                problems.len()
+            ),
+            ArakiError::LockSpec(message) => write!(f, "{message}"),
+            ArakiError::RevisionNotFound(revision) => {
+                write!(f, "No tag, branch or commit found for {revision}")
+            }
+            ArakiError::TagExists(name) => write!(
+                f,
+                "Tag '{name}' already exists. Use --force to move it to the current lockspec."
+            ),
+            ArakiError::TagCheckedOut(name) => write!(
+                f,
+                "Tag '{name}' is checked out. Check out another tag or `latest` before deleting it."
+            ),
+            ArakiError::UntrustedTag { revision, reason } => write!(
+                f,
+                "Refusing to use {revision}, since it has no trusted signature: {reason}. \
+                    Trusted signers are configured in ~/.araki/config.toml."
+            ),
+            ArakiError::Signing(message) => write!(f, "{message}"),
+            ArakiError::DetachedHead => write!(
+                f,
+                "HEAD is detached, so the new commit wouldn't be on any branch and `araki push` \
+                    wouldn't send it. Run `araki checkout latest` first, or pass --branch <NAME> \
+                    to create a branch for it."
+            ),
+            ArakiError::NothingToTag => write!(
+                f,
+                "The lockspec hasn't changed since the last commit. Pass --allow-empty to tag the \
+                    current commit anyway."
+            ),
+            ArakiError::TagOnRemote(name) => write!(
+                f,
+                "Tag '{name}' has already been pushed to the remote and can't be moved. Create a \
+                    new tag instead."
+            ),
+            ArakiError::InvalidTagName { name, reason } => {
+                write!(f, "'{name}' is not a valid tag name: {reason}")
+            }
+            ArakiError::InvalidRemote { remote, reason } => {
+                write!(f, "{remote} is not a valid lockspec repository: {reason}")
+            }
+            ArakiError::Git { context, source } => write!(f, "{context}: {source}"),
+            ArakiError::Io { context, source } => write!(f, "{context}: {source}"),
+            ArakiError::Backend { context, source } => write!(f, "{context}: {source}"),
+            ArakiError::Copy(err) => write!(f, "{err}"),
+            ArakiError::Rollback { error, rollback } => write!(
+                f,
+                "{error}\nUnable to restore the directory to its original state: {rollback}"
+            ),
+            ArakiError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ArakiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArakiError::Git { source, .. } => Some(source),
+            ArakiError::Io { source, .. } => Some(source),
+            ArakiError::Backend { source, .. } => Some(source.as_ref()),
+            ArakiError::Copy(err) => Some(err),
+            ArakiError::Rollback { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<CopyError> for ArakiError {
+    fn from(err: CopyError) -> Self {
+        ArakiError::Copy(err)
+    }
+}
+
+impl From<String> for ArakiError {
+    fn from(message: String) -> Self {
+        ArakiError::Other(message)
+    }
+}
@@ -0,0 +1,190 @@
+use git2::{Config, Repository, Signature};
+use log::debug;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::backends::{self, Backend};
+use crate::config::ArakiConfig;
+use crate::error::ArakiError;
+
+/// A person or bot that commits and tags are attributed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    pub fn new(name: impl Into<String>, email: impl Into<String>) -> Self {
+        Identity {
+            name: name.into(),
+            email: email.into(),
+        }
+    }
+
+    /// Build a git signature for the identity, timestamped now.
+    pub fn signature(&self) -> Result<Signature<'static>, ArakiError> {
+        Signature::now(&self.name, &self.email).map_err(ArakiError::git(format!(
+            "{self} is not a valid git identity"
+        )))
+    }
+}
+
+impl FromStr for Identity {
+    type Err = String;
+
+    /// Parse an identity written as `Name <email>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{s}' is not of the form 'Name <email>'");
+        let (name, rest) = s.split_once('<').ok_or_else(invalid)?;
+        let email = rest.strip_suffix('>').ok_or_else(invalid)?;
+        let (name, email) = (name.trim(), email.trim());
+        if name.is_empty() || email.is_empty() || email.contains(['<', '>']) {
+            return Err(invalid());
+        }
+        Ok(Identity::new(name, email))
+    }
+}
+
+impl Display for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+impl From<&Signature<'_>> for Identity {
+    fn from(signature: &Signature<'_>) -> Self {
+        Identity::new(
+            String::from_utf8_lossy(signature.name_bytes()),
+            String::from_utf8_lossy(signature.email_bytes()),
+        )
+    }
+}
+
+/// Parse an identity from the araki configuration.
+///
+/// * `key`: Name of the configuration key, for error messages
+/// * `value`: Value of the key
+fn parse_config_identity(key: &str, value: Option<&str>) -> Result<Option<Identity>, ArakiError> {
+    value
+        .map(|value| {
+            value.parse().map_err(|err| {
+                ArakiError::LockSpec(format!("Invalid {key} in the araki config: {err}"))
+            })
+        })
+        .transpose()
+}
+
+/// Read `user.name` and `user.email` from git config.
+///
+/// * `repo`: araki git repository whose config should be used; the global git config is used
+///   if there is none yet
+fn git_config_identity(repo: Option<&Repository>) -> Option<Identity> {
+    match repo {
+        Some(repo) => repo
+            .signature()
+            .ok()
+            .map(|signature| Identity::from(&signature)),
+        None => {
+            let config = Config::open_default().ok()?;
+            Some(Identity::new(
+                config.get_string("user.name").ok()?,
+                config.get_string("user.email").ok()?,
+            ))
+        }
+    }
+}
+
+/// Resolve the author without contacting the backend: from the araki configuration, then from
+/// git config (`user.name` and `user.email`).
+///
+/// * `repo`: araki git repository whose config should be used, if it exists yet
+/// * `config`: araki configuration of the lockspec
+pub fn resolve_local_author(
+    repo: Option<&Repository>,
+    config: &ArakiConfig,
+) -> Result<Option<Identity>, ArakiError> {
+    if let Some(author) = parse_config_identity("author", config.author.as_deref())? {
+        debug!(author:% = author; "Using the author from the araki config");
+        return Ok(Some(author));
+    }
+    if let Some(author) = git_config_identity(repo) {
+        debug!(author:% = author; "Using the author from git config");
+        return Ok(Some(author));
+    }
+    Ok(None)
+}
+
+/// Resolve who commits and tags should be attributed to, in order of precedence:
+///
+/// 1. `explicit`, e.g. passed with `--author`
+/// 2. `author` in the araki configuration
+/// 3. `user.name` and `user.email` in git config
+/// 4. The user araki is authenticated with on the backend
+///
+/// * `repo`: araki git repository whose config should be used, if it exists yet
+/// * `explicit`: Author passed explicitly, if any
+/// * `config`: araki configuration of the lockspec
+pub async fn resolve_author(
+    repo: Option<&Repository>,
+    explicit: Option<&Identity>,
+    config: &ArakiConfig,
+) -> Result<Identity, ArakiError> {
+    if let Some(author) = explicit {
+        return Ok(author.clone());
+    }
+    if let Some(author) = resolve_local_author(repo, config)? {
+        return Ok(author);
+    }
+
+    let backend = backends::get_current_backend()
+        .map_err(ArakiError::backend("Unable to get the current backend"))?;
+    let user = backend.get_user().await.map_err(ArakiError::backend(
+        "Unable to determine the author: pass --author, set `author` in the araki config or \
+            `user.name` and `user.email` in git config, or log in with `araki auth login`",
+    ))?;
+    let author = Identity::new(
+        user.name.clone().unwrap_or_else(|| user.login.clone()),
+        user.email
+            .clone()
+            .unwrap_or_else(|| backend.noreply_email(&user)),
+    );
+    debug!(author:% = author; "Using the author from the backend user");
+    Ok(author)
+}
+
+/// Resolve the committer of commits and tags: `explicit`, then `committer` in the araki
+/// configuration, falling back to the author.
+///
+/// * `explicit`: Committer passed explicitly, if any
+/// * `config`: araki configuration of the lockspec
+/// * `author`: Resolved author
+pub fn resolve_committer(
+    explicit: Option<&Identity>,
+    config: &ArakiConfig,
+    author: &Identity,
+) -> Result<Identity, ArakiError> {
+    if let Some(committer) = explicit {
+        return Ok(committer.clone());
+    }
+    Ok(
+        parse_config_identity("committer", config.committer.as_deref())?
+            .unwrap_or_else(|| author.clone()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity() {
+        let identity: Identity = "Jane Doe <jane@example.com>".parse().unwrap();
+        assert_eq!(identity, Identity::new("Jane Doe", "jane@example.com"));
+        assert_eq!(identity.to_string(), "Jane Doe <jane@example.com>");
+
+        assert!("jane@example.com".parse::<Identity>().is_err());
+        assert!("<jane@example.com>".parse::<Identity>().is_err());
+        assert!("Jane <>".parse::<Identity>().is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! araki manages and versions pixi environments (and other lockspecs) with git.
+//!
+//! Most operations go through a [`Workspace`]: a directory containing a lockspec, along with the
+//! `.araki-git` repository tracking its versions. Every fallible operation returns an
+//! [`ArakiError`].
+
+pub mod backends;
+pub mod common;
+pub mod config;
+pub mod diff;
+pub mod error;
+pub mod identity;
+pub mod lockspecs;
+pub mod registry;
+pub mod remote;
+pub mod signing;
+pub mod transaction;
+pub mod version;
+pub mod workspace;
+
+pub use error::ArakiError;
+pub use workspace::Workspace;
@@ -0,0 +1,689 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Display};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::common::ARAKI_GIT_DIR_NAME;
+use crate::config::ArakiConfig;
+use crate::diff::compare_versions;
+
+pub mod conda_lock;
+pub mod pixi;
+pub mod uv;
+
+pub use conda_lock::CondaLock;
+pub use pixi::Pixi;
+pub use uv::Uv;
+
+/// A single package pinned in a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Package {
+    /// Environment the package belongs to, e.g. `default`
+    pub environment: String,
+    /// Platform the package was locked for, e.g. `linux-64`
+    pub platform: String,
+    pub name: String,
+    pub version: String,
+}
+
+/// A dependency declared in a specfile.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dependency {
+    /// Environment the dependency belongs to, e.g. `default`
+    pub environment: String,
+    /// Platform the dependency applies to, or `None` if it applies to every locked platform
+    pub platform: Option<String>,
+    pub name: String,
+    /// Version spec of the dependency, e.g. `>=3.12`. Empty if unconstrained
+    pub spec: String,
+}
+
+pub trait LockSpecFormat: Debug + Send + Sync {
+    /// Short name of the format, e.g. `pixi`.
+    fn name(&self) -> &'static str;
+
+    /// Name of the file users edit to declare their dependencies.
+    fn specfile_name(&self) -> &'static str;
+
+    /// Name of the file containing the fully resolved environment.
+    fn lockfile_name(&self) -> &'static str;
+
+    /// Build the command which installs the environment from the lockfile, without solving.
+    ///
+    /// The caller is responsible for setting the working directory of the command.
+    fn install_command(&self) -> Command;
+
+    /// Directories the install command creates inside the lockspec directory, e.g. `.pixi/`.
+    /// They are ignored by the git repository containing the lockspec, if any.
+    fn install_dirs(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Build the arguments which restrict the install command to a single environment of the
+    /// lockspec. Fails for formats which don't have environments.
+    ///
+    /// * `environment`: Name of the environment, e.g. `default`
+    fn environment_args(&self, environment: &str) -> Result<Vec<String>, String> {
+        Err(format!(
+            "{} lockspecs have no environments, so '{environment}' can't be installed on its own",
+            self.name()
+        ))
+    }
+
+    /// Parse the packages pinned in a lockfile.
+    ///
+    /// * `lockfile`: Contents of the lockfile
+    fn parse_packages(&self, lockfile: &str) -> Result<Vec<Package>, String>;
+
+    /// Parse the dependencies declared in a specfile.
+    ///
+    /// * `specfile`: Contents of the specfile
+    fn parse_dependencies(&self, specfile: &str) -> Result<Vec<Dependency>, String>;
+
+    /// Parse the names of the environments declared in a specfile, including those without any
+    /// dependencies.
+    ///
+    /// * `specfile`: Contents of the specfile
+    fn parse_environments(&self, specfile: &str) -> Result<BTreeSet<String>, String> {
+        Ok(self
+            .parse_dependencies(specfile)?
+            .into_iter()
+            .map(|dependency| dependency.environment)
+            .collect())
+    }
+
+    /// Parse the names of the environments locked in a lockfile, including those without any
+    /// packages.
+    ///
+    /// * `lockfile`: Contents of the lockfile
+    fn parse_locked_environments(&self, lockfile: &str) -> Result<BTreeSet<String>, String> {
+        Ok(self
+            .parse_packages(lockfile)?
+            .into_iter()
+            .map(|package| package.environment)
+            .collect())
+    }
+
+    /// Read the araki configuration from the contents of a specfile.
+    ///
+    /// Returns the default configuration if the specfile has no araki metadata.
+    ///
+    /// * `specfile`: Contents of the specfile
+    fn araki_config(&self, specfile: &str) -> Result<ArakiConfig, String>;
+
+    /// Add the araki metadata containing the lockspec name to the contents of a specfile.
+    ///
+    /// Returns the new contents of the specfile, or `None` if the metadata is already present.
+    ///
+    /// * `specfile`: Contents of the specfile
+    /// * `lockspec_name`: Lockspec name to write to the file
+    fn add_araki_metadata(
+        &self,
+        specfile: &str,
+        lockspec_name: &str,
+    ) -> Result<Option<String>, String>;
+
+    /// Environment management tools which the shims should let through inside lockspecs of
+    /// this format, because they are the tools that manage it.
+    fn exempt_tools(&self) -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// All supported lockspec formats, in the order they are detected.
+pub static FORMATS: &[&dyn LockSpecFormat] = &[&Pixi, &CondaLock, &Uv];
+
+/// Split a conda match spec or a PEP 508 requirement into a package name and a version spec.
+///
+/// * `requirement`: e.g. `conda-forge::numpy >=2`, `python=3.12` or `requests[socks]==2.32`
+pub fn parse_requirement(requirement: &str) -> (String, String) {
+    let requirement = requirement.trim();
+    // Drop any channel prefix, e.g. `conda-forge::`
+    let requirement = requirement
+        .rsplit_once("::")
+        .map_or(requirement, |(_, rest)| rest);
+    let end = requirement
+        .find(|c: char| c.is_whitespace() || "=<>!~[;@(".contains(c))
+        .unwrap_or(requirement.len());
+    let (name, spec) = requirement.split_at(end);
+    (name.to_string(), spec.trim().to_string())
+}
+
+/// Normalize a package name so that conda and PyPI spellings of the same name compare equal.
+///
+/// * `name`: Package name
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Check whether a version satisfies a version spec.
+///
+/// Supports conda match specs and PEP 440 specifiers: `*`, `3.13.*`, `=3.13`, `==3.13.1`, `!=`,
+/// `>=`, `<=`, `>`, `<`, `~=`, constraints joined with `,` (and) or `|` (or), and extras or a
+/// build string around the version. A version without an operator matches as a prefix, like
+/// `=`. Returns `None` if the spec can't be evaluated, e.g. a URL or environment markers.
+///
+/// * `version`: Locked version, e.g. `3.13.0`
+/// * `spec`: Version spec, e.g. `>=3.12,<3.14`
+pub fn satisfies(version: &str, spec: &str) -> Option<bool> {
+    let mut spec = spec.trim();
+    // Extras such as `[socks]` don't constrain the version
+    if let Some(rest) = spec.strip_prefix('[') {
+        spec = rest.split_once(']')?.1.trim();
+    }
+    if spec.contains([';', '@']) {
+        return None;
+    }
+    let mut any = false;
+    for alternative in spec.split('|') {
+        let mut all = true;
+        for constraint in alternative.split(',') {
+            all &= satisfies_constraint(version, constraint.trim())?;
+        }
+        any |= all;
+    }
+    Some(any)
+}
+
+/// Check whether a version satisfies a single constraint of a version spec, e.g. `>=3.12`.
+fn satisfies_constraint(version: &str, constraint: &str) -> Option<bool> {
+    let operator = ["===", "==", "!=", "~=", ">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find(|operator| constraint.starts_with(operator))
+        .unwrap_or("");
+    // Anything after the version, e.g. a conda build string, doesn't constrain it
+    let bound = constraint[operator.len()..]
+        .split_whitespace()
+        .next()
+        .unwrap_or("*");
+    if !bound
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "._*+!-".contains(c))
+    {
+        return None;
+    }
+    let prefix = bound.strip_suffix(".*").or(bound.strip_suffix('*'));
+    let ordering = || compare_versions(trim_zeros(version), trim_zeros(bound));
+    Some(match (operator, prefix) {
+        (_, Some("")) => true,
+        ("" | "=" | "==", Some(prefix)) => has_prefix(version, prefix),
+        ("!=", Some(prefix)) => !has_prefix(version, prefix),
+        ("" | "=", None) => has_prefix(version, bound),
+        ("===", _) => version == bound,
+        ("==", None) => ordering() == Ordering::Equal,
+        ("!=", None) => ordering() != Ordering::Equal,
+        (">=", _) => ordering() != Ordering::Less,
+        ("<=", _) => ordering() != Ordering::Greater,
+        (">", _) => ordering() == Ordering::Greater,
+        ("<", _) => ordering() == Ordering::Less,
+        ("~=", None) => {
+            let (release, _) = bound.rsplit_once('.')?;
+            ordering() != Ordering::Less && has_prefix(version, release)
+        }
+        _ => return None,
+    })
+}
+
+/// Check whether a version starts with the components of a prefix, e.g. `3.13.1` with `3.13`.
+fn has_prefix(version: &str, prefix: &str) -> bool {
+    let prefix = trim_zeros(prefix);
+    let version = trim_zeros(version);
+    version == prefix
+        || version
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(['.', '-', '+']))
+}
+
+/// Remove the trailing zero components of a version, which conda ignores: `3.13.0` equals
+/// `3.13`.
+fn trim_zeros(version: &str) -> &str {
+    let mut version = version;
+    while let Some(rest) = version.strip_suffix(".0") {
+        version = rest;
+    }
+    version
+}
+
+/// Check that a lockfile is consistent with its specfile.
+///
+/// Both files must parse, every environment and platform declared in the specfile must be
+/// locked, and every declared dependency must be pinned in the lockfile at a version which
+/// satisfies its spec. Returns the list of problems found, which is empty if the lockspec is
+/// consistent.
+///
+/// * `format`: Format of the lockspec
+/// * `specfile`: Contents of the specfile
+/// * `lockfile`: Contents of the lockfile
+pub fn check_contents(format: &dyn LockSpecFormat, specfile: &str, lockfile: &str) -> Vec<String> {
+    let specfile_name = format.specfile_name();
+    let lockfile_name = format.lockfile_name();
+    let declared = format
+        .parse_dependencies(specfile)
+        .and_then(|dependencies| Ok((dependencies, format.parse_environments(specfile)?)));
+    let pinned = format
+        .parse_packages(lockfile)
+        .and_then(|packages| Ok((packages, format.parse_locked_environments(lockfile)?)));
+    let ((dependencies, declared), (packages, pinned)) = match (declared, pinned) {
+        (Ok(declared), Ok(pinned)) => (declared, pinned),
+        (declared, pinned) => {
+            return [
+                declared.err().map(|err| format!("{specfile_name}: {err}")),
+                pinned.err().map(|err| format!("{lockfile_name}: {err}")),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+        }
+    };
+
+    let mut locked: BTreeMap<&str, BTreeMap<&str, BTreeMap<String, Vec<&str>>>> = BTreeMap::new();
+    for package in &packages {
+        locked
+            .entry(&package.environment)
+            .or_default()
+            .entry(&package.platform)
+            .or_default()
+            .entry(normalize_name(&package.name))
+            .or_default()
+            .push(&package.version);
+    }
+
+    let mut problems = BTreeSet::new();
+    for environment in declared.difference(&pinned) {
+        problems.insert(format!(
+            "Environment '{environment}' is declared in {specfile_name} but not locked in \
+                {lockfile_name}"
+        ));
+    }
+    for dependency in &dependencies {
+        // Environments which aren't locked at all have already been reported above
+        let Some(platforms) = locked.get(dependency.environment.as_str()) else {
+            continue;
+        };
+        let targets: Vec<&str> = match &dependency.platform {
+            Some(platform) => vec![platform.as_str()],
+            None => platforms.keys().copied().collect(),
+        };
+        for platform in targets {
+            match platforms.get(platform) {
+                None => {
+                    problems.insert(format!(
+                        "Platform '{platform}' of environment '{}' is not locked in \
+                            {lockfile_name}",
+                        dependency.environment
+                    ));
+                }
+                Some(names) => match names.get(&normalize_name(&dependency.name)) {
+                    None => {
+                        problems.insert(format!(
+                            "Dependency '{}' of environment '{}' is missing from \
+                                {lockfile_name} for {platform}",
+                            dependency.name, dependency.environment
+                        ));
+                    }
+                    // Specs which can't be evaluated are assumed to be satisfied
+                    Some(versions)
+                        if !versions.iter().any(|version| {
+                            satisfies(version, &dependency.spec).unwrap_or(true)
+                        }) =>
+                    {
+                        problems.insert(format!(
+                            "Dependency '{}' of environment '{}' requires {}, but {lockfile_name} \
+                                locks {} for {platform}",
+                            dependency.name,
+                            dependency.environment,
+                            dependency.spec,
+                            versions.join(", ")
+                        ));
+                    }
+                    Some(_) => (),
+                },
+            }
+        }
+    }
+    problems.into_iter().collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct LockSpec {
+    pub path: PathBuf,
+    pub format: &'static dyn LockSpecFormat,
+}
+
+impl Display for LockSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} lockspec: {:?}", self.format.name(), self.path)
+    }
+}
+
+impl LockSpec {
+    pub fn specfile(&self) -> PathBuf {
+        self.path.join(self.format.specfile_name())
+    }
+
+    pub fn lockfile(&self) -> PathBuf {
+        self.path.join(self.format.lockfile_name())
+    }
+
+    /// Read the araki configuration from the specfile.
+    pub fn config(&self) -> Result<ArakiConfig, String> {
+        let specfile = self.specfile();
+        let contents = fs::read_to_string(&specfile)
+            .map_err(|err| format!("Unable to read file {specfile:?}: {err}"))?;
+        self.format
+            .araki_config(&contents)
+            .map_err(|err| format!("{specfile:?}: {err}"))
+    }
+
+    /// Patterns matching the files tracked in the araki git repository, relative to `self.path`:
+    /// the specfile, the lockfile and any `include` patterns from the araki configuration.
+    pub fn tracked_patterns(&self) -> Result<Vec<String>, String> {
+        let mut patterns = vec![
+            self.format.specfile_name().to_string(),
+            self.format.lockfile_name().to_string(),
+        ];
+        for pattern in self.config()?.include {
+            let path = Path::new(&pattern);
+            if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                return Err(format!(
+                    "Included files must be inside the lockspec directory: {pattern}"
+                ));
+            }
+            patterns.push(pattern);
+        }
+        Ok(patterns)
+    }
+
+    /// Files tracked in the araki git repository which currently exist, relative to `self.path`.
+    ///
+    /// Included directories are expanded to the files they contain.
+    pub fn tracked_files(&self) -> Result<Vec<PathBuf>, String> {
+        let mut files = BTreeSet::new();
+        // The directory may contain characters with a meaning in globs, e.g. `[`
+        let base = PathBuf::from(glob::Pattern::escape(&self.path.to_string_lossy()));
+        for pattern in self.tracked_patterns()? {
+            let full_pattern = base.join(&pattern);
+            let matches = glob::glob(&full_pattern.to_string_lossy())
+                .map_err(|err| format!("Invalid include pattern '{pattern}': {err}"))?;
+            for entry in matches {
+                let path = entry.map_err(|err| format!("Unable to read {pattern}: {err}"))?;
+                collect_files(&path, &mut files)
+                    .map_err(|err| format!("Unable to read {path:?}: {err}"))?;
+            }
+        }
+        Ok(files
+            .into_iter()
+            .filter_map(|file| file.strip_prefix(&self.path).ok().map(Path::to_path_buf))
+            .filter(|file| !file.starts_with(ARAKI_GIT_DIR_NAME))
+            .collect())
+    }
+
+    /// Check whether a path relative to `self.path` is matched by the tracked patterns.
+    ///
+    /// * `file`: Path relative to the lockspec directory
+    pub fn is_tracked(&self, file: &Path) -> Result<bool, String> {
+        for pattern in self.tracked_patterns()? {
+            let matcher = glob::Pattern::new(&pattern)
+                .map_err(|err| format!("Invalid include pattern '{pattern}': {err}"))?;
+            // Patterns naming a directory track everything inside it
+            if matcher.matches_path(file)
+                || file
+                    .ancestors()
+                    .skip(1)
+                    .any(|dir| matcher.matches_path(dir))
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Construct a LockSpec from the given path.
+    ///
+    /// The format of the lockspec is detected from the files present; if several formats match,
+    /// the first one in `FORMATS` is used.
+    ///
+    /// * `path`: Path to a directory containing a lockspec, e.g. a pixi.lock and a pixi.toml
+    pub fn from_path<T>(path: T) -> Result<LockSpec, String>
+    where
+        T: AsRef<Path> + std::fmt::Debug,
+    {
+        FORMATS
+            .iter()
+            .map(|format| LockSpec {
+                path: path.as_ref().to_path_buf(),
+                format: *format,
+            })
+            .find(|ls| ls.files_exist())
+            .ok_or(format!("No lockspec files found in {:?}", path))
+    }
+
+    /// Check whether a lockspec (lockfile+specfile) exist in self.path.
+    pub fn files_exist(&self) -> bool {
+        self.lockfile().exists() && self.specfile().exists()
+    }
+
+    /// Run the install command of the lockspec format in self.path.
+    ///
+    /// The output of the install command is sent to stderr, so that stdout only contains the
+    /// output of araki itself.
+    ///
+    /// * `environment`: Only install this environment of the lockspec, if given
+    pub fn install(&self, environment: Option<&str>) -> Result<(), String> {
+        let mut command = self.format.install_command();
+        if let Some(environment) = environment {
+            command.args(self.format.environment_args(environment)?);
+        }
+        let status = command
+            .current_dir(&self.path)
+            .stdout(std::io::stderr())
+            .status()
+            .map_err(|err| format!("Unable to run {:?}: {err}", command.get_program()))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{:?} exited with {status}", command.get_program()))
+        }
+    }
+
+    /// Parse the packages pinned in the lockfile.
+    pub fn packages(&self) -> Result<Vec<Package>, String> {
+        let lockfile = self.lockfile();
+        let contents = fs::read_to_string(&lockfile)
+            .map_err(|err| format!("Unable to read file {lockfile:?}: {err}"))?;
+        self.format.parse_packages(&contents)
+    }
+
+    /// Parse the dependencies declared in the specfile.
+    pub fn dependencies(&self) -> Result<Vec<Dependency>, String> {
+        let specfile = self.specfile();
+        let contents = fs::read_to_string(&specfile)
+            .map_err(|err| format!("Unable to read file {specfile:?}: {err}"))?;
+        self.format.parse_dependencies(&contents)
+    }
+
+    /// Check that the lockfile is consistent with the specfile. See `check_contents`.
+    pub fn check(&self) -> Result<Vec<String>, String> {
+        let read = |path: PathBuf| {
+            fs::read_to_string(&path).map_err(|err| format!("Unable to read file {path:?}: {err}"))
+        };
+        Ok(check_contents(
+            self.format,
+            &read(self.specfile())?,
+            &read(self.lockfile())?,
+        ))
+    }
+
+    /// Ensure that the araki metadata containing the lockspec name is written to the specfile.
+    ///
+    /// * `lockspec_name`: Lockspec name to write to the file
+    pub fn ensure_araki_metadata(&self, lockspec_name: &str) -> Result<(), String> {
+        let specfile = self.specfile();
+
+        let file = std::fs::read_to_string(&specfile)
+            .map_err(|_| format!("Unable to read file {specfile:?}"))?;
+
+        if let Some(contents) = self
+            .format
+            .add_araki_metadata(&file, lockspec_name)
+            .map_err(|err| format!("{specfile:?}: {err}"))?
+        {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&specfile)
+                .map_err(|err| {
+                    format!(
+                        "Unable to open araki config at {specfile:?} for writing.\nReason: {err}"
+                    )
+                })?;
+            file.write_all(contents.as_bytes()).map_err(|err| {
+                format!("Unable to write araki config to {specfile:?}.\nReason: {err}")
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect a file, or all the files inside a directory recursively.
+///
+/// * `path`: File or directory
+/// * `files`: Set to add the files to
+fn collect_files(path: &Path, files: &mut BTreeSet<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else {
+        files.insert(path.to_path_buf());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_parse_requirement() {
+        let cases = [
+            ("conda-forge::numpy >=2", ("numpy", ">=2")),
+            ("python=3.12", ("python", "=3.12")),
+            ("requests[socks]==2.32", ("requests", "[socks]==2.32")),
+            ("pytest", ("pytest", "")),
+        ];
+        for (requirement, (name, spec)) in cases {
+            assert_eq!(
+                parse_requirement(requirement),
+                (name.to_string(), spec.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_tracked_files_in_directory_with_glob_characters() {
+        let dir = temp_dir().join(format!("araki-test-[1]-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pixi.toml"), "[workspace]\nname = \"test\"\n").unwrap();
+        fs::write(dir.join("pixi.lock"), "version: 6\n").unwrap();
+
+        let lockspec = LockSpec::from_path(&dir).unwrap();
+        let files = lockspec.tracked_files();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            files.unwrap(),
+            [PathBuf::from("pixi.lock"), PathBuf::from("pixi.toml")]
+        );
+    }
+
+    #[test]
+    fn test_satisfies() {
+        let cases = [
+            ("3.13.0", "", Some(true)),
+            ("3.13.0", "*", Some(true)),
+            ("3.13.0", "3.13.*", Some(true)),
+            ("3.13.0", "3.12.*", Some(false)),
+            ("3.13.0", "=3.13", Some(true)),
+            ("3.13.0", "3.1", Some(false)),
+            ("3.13.0", "==3.13", Some(true)),
+            ("3.13.1", "==3.13", Some(false)),
+            ("3.13.0", ">=3.12,<3.14", Some(true)),
+            ("3.15.0", ">=3.20", Some(false)),
+            ("3.15.0", "<3.14|>=3.15", Some(true)),
+            ("2.32.3", "~=2.31", Some(true)),
+            ("3.0.0", "~=2.31", Some(false)),
+            ("2.32.3", "[socks]>=2.32", Some(true)),
+            ("3.13.0", "3.13.* *_cp313", Some(true)),
+            ("1.0", ">=1; python_version < '3.11'", None),
+        ];
+        for (version, spec, expected) in cases {
+            assert_eq!(satisfies(version, spec), expected, "{version} {spec}");
+        }
+    }
+
+    #[test]
+    fn test_check_contents() {
+        let manifest = r#"
+[workspace]
+name = "test"
+channels = ["conda-forge"]
+platforms = ["linux-64", "osx-arm64"]
+
+[dependencies]
+python = "3.13.*"
+numpy = "*"
+"#;
+        let lockfile = r#"
+version: 6
+environments:
+  default:
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/python-3.13.0-h9ebbce0_100_cp313.conda
+packages:
+- conda: https://conda.anaconda.org/conda-forge/linux-64/python-3.13.0-h9ebbce0_100_cp313.conda
+"#;
+        assert_eq!(
+            check_contents(&Pixi, manifest, lockfile),
+            vec![
+                "Dependency 'numpy' of environment 'default' is missing from pixi.lock for \
+                    linux-64",
+                "Platform 'osx-arm64' of environment 'default' is not locked in pixi.lock",
+            ]
+        );
+        assert_eq!(check_contents(&Pixi, "not toml [", lockfile).len(), 1);
+
+        // The lockfile is stale: python was locked before the manifest required a newer one
+        let stale = manifest
+            .replace("\"3.13.*\"", "\">=3.20\"")
+            .replace("osx-arm64", "linux-64")
+            .replace("numpy = \"*\"\n", "");
+        assert_eq!(
+            check_contents(&Pixi, &stale, lockfile),
+            vec![
+                "Dependency 'python' of environment 'default' requires >=3.20, but pixi.lock \
+                    locks 3.13.0 for linux-64"
+            ]
+        );
+
+        // An environment without any dependencies of its own must still be locked
+        let empty = stale.replace("\">=3.20\"", "\"3.13.*\"")
+            + r#"
+[feature.empty]
+
+[environments]
+empty = { features = ["empty"], no-default-feature = true }
+"#;
+        assert_eq!(
+            check_contents(&Pixi, &empty, lockfile),
+            vec!["Environment 'empty' is declared in pixi.toml but not locked in pixi.lock"]
+        );
+    }
+}
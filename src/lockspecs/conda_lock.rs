@@ -0,0 +1,217 @@
+use serde::Deserialize;
+use std::process::Command;
+
+use super::{Dependency, LockSpecFormat, Package, parse_requirement};
+use crate::config::ArakiConfig;
+
+/// Lockspecs managed by conda-lock: `environment.yml` and `conda-lock.yml`.
+#[derive(Debug)]
+pub struct CondaLock;
+
+#[derive(Deserialize, Debug)]
+struct CondaLockfile {
+    #[serde(default)]
+    package: Vec<CondaLockPackage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CondaLockPackage {
+    name: String,
+    version: String,
+    platform: String,
+    #[serde(default = "CondaLock::default_category")]
+    category: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CondaEnvironmentFile {
+    #[serde(default)]
+    dependencies: Vec<CondaEnvironmentDependency>,
+    #[serde(default)]
+    platforms: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum CondaEnvironmentDependency {
+    Conda(String),
+    Pip { pip: Vec<String> },
+}
+
+impl CondaLock {
+    /// Prefix the environment is installed to, relative to the lockspec directory
+    const PREFIX: &str = ".conda-env";
+
+    fn default_category() -> String {
+        "main".to_string()
+    }
+}
+
+impl LockSpecFormat for CondaLock {
+    fn name(&self) -> &'static str {
+        "conda-lock"
+    }
+
+    fn specfile_name(&self) -> &'static str {
+        "environment.yml"
+    }
+
+    fn lockfile_name(&self) -> &'static str {
+        "conda-lock.yml"
+    }
+
+    fn install_command(&self) -> Command {
+        let mut command = Command::new("conda-lock");
+        command.args(["install", "--prefix", Self::PREFIX, self.lockfile_name()]);
+        command
+    }
+
+    fn install_dirs(&self) -> &'static [&'static str] {
+        &[".conda-env/"]
+    }
+
+    fn parse_packages(&self, lockfile: &str) -> Result<Vec<Package>, String> {
+        let lock: CondaLockfile = serde_yaml::from_str(lockfile)
+            .map_err(|err| format!("Unable to parse conda-lock lockfile: {err}"))?;
+
+        // conda-lock has no notion of named environments; the closest equivalent is the
+        // dependency category (`main`, `dev`, ...)
+        let mut packages: Vec<Package> = lock
+            .package
+            .into_iter()
+            .map(|pkg| Package {
+                environment: pkg.category,
+                platform: pkg.platform,
+                name: pkg.name,
+                version: pkg.version,
+            })
+            .collect();
+        packages.sort();
+        Ok(packages)
+    }
+
+    fn parse_dependencies(&self, specfile: &str) -> Result<Vec<Dependency>, String> {
+        let environment: CondaEnvironmentFile = serde_yaml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse environment file: {err}"))?;
+
+        // Without a `platforms` key the platforms are chosen on the conda-lock command line, so
+        // the dependencies apply to whichever platforms were locked
+        let platforms: Vec<Option<String>> = if environment.platforms.is_empty() {
+            vec![None]
+        } else {
+            environment.platforms.into_iter().map(Some).collect()
+        };
+
+        let requirements: Vec<&String> = environment
+            .dependencies
+            .iter()
+            .flat_map(|dep| match dep {
+                CondaEnvironmentDependency::Conda(spec) => vec![spec],
+                CondaEnvironmentDependency::Pip { pip } => pip.iter().collect(),
+            })
+            .collect();
+
+        let mut dependencies = vec![];
+        for platform in &platforms {
+            for requirement in &requirements {
+                let (name, spec) = parse_requirement(requirement);
+                dependencies.push(Dependency {
+                    environment: Self::default_category(),
+                    platform: platform.clone(),
+                    name,
+                    spec,
+                });
+            }
+        }
+        dependencies.sort();
+        Ok(dependencies)
+    }
+
+    fn araki_config(&self, specfile: &str) -> Result<ArakiConfig, String> {
+        let yaml_data: serde_yaml::Mapping = serde_yaml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse specfile as valid yaml.\nReason: {err}"))?;
+        match yaml_data.get("araki") {
+            Some(araki) => serde_yaml::from_value(araki.clone())
+                .map_err(|err| format!("Invalid araki configuration: {err}")),
+            None => Ok(ArakiConfig::default()),
+        }
+    }
+
+    fn add_araki_metadata(
+        &self,
+        specfile: &str,
+        lockspec_name: &str,
+    ) -> Result<Option<String>, String> {
+        let mut yaml_data: serde_yaml::Mapping = serde_yaml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse specfile as valid yaml.\nReason: {err}"))?;
+
+        if yaml_data.contains_key("araki") {
+            return Ok(None);
+        }
+        let mut araki_table = serde_yaml::Mapping::new();
+        araki_table.insert("lockspec_name".into(), lockspec_name.into());
+        yaml_data.insert("araki".into(), serde_yaml::Value::Mapping(araki_table));
+        serde_yaml::to_string(&yaml_data)
+            .map(Some)
+            .map_err(|err| format!("Unable to serialize specfile as yaml: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_packages() {
+        let lockfile = r#"
+version: 1
+metadata:
+  platforms: [linux-64, osx-arm64]
+package:
+- name: numpy
+  version: 2.3.1
+  manager: conda
+  platform: osx-arm64
+  category: main
+- name: numpy
+  version: 2.3.1
+  manager: conda
+  platform: linux-64
+"#;
+        let packages = CondaLock
+            .parse_packages(lockfile)
+            .expect("should be able to parse the lockfile");
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].platform, "linux-64");
+        assert_eq!(packages[0].environment, "main");
+    }
+
+    #[test]
+    fn test_parse_dependencies() {
+        let specfile = r#"
+name: test
+channels: [conda-forge]
+dependencies:
+  - conda-forge::python=3.12
+  - numpy >=2
+  - pip:
+    - requests==2.32.3
+"#;
+        let dependencies = CondaLock
+            .parse_dependencies(specfile)
+            .expect("should be able to parse the environment file");
+        let rendered: Vec<(&str, &str)> = dependencies
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.spec.as_str()))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                ("numpy", ">=2"),
+                ("python", "=3.12"),
+                ("requests", "==2.32.3")
+            ]
+        );
+        assert!(dependencies.iter().all(|dep| dep.platform.is_none()));
+    }
+}
@@ -0,0 +1,424 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::process::Command;
+use toml::Table;
+
+use super::{Dependency, LockSpecFormat, Package};
+use crate::config::ArakiConfig;
+
+/// Lockspecs managed by pixi: `pixi.toml` and `pixi.lock`.
+#[derive(Debug)]
+pub struct Pixi;
+
+#[derive(Deserialize, Debug)]
+struct PixiLockfile {
+    environments: BTreeMap<String, PixiLockEnvironment>,
+    #[serde(default)]
+    packages: Vec<serde_yaml::Mapping>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PixiLockEnvironment {
+    #[serde(default)]
+    packages: BTreeMap<String, Vec<serde_yaml::Mapping>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PixiManifest {
+    #[serde(alias = "project")]
+    workspace: PixiWorkspace,
+    #[serde(flatten)]
+    default_feature: PixiFeature,
+    #[serde(default)]
+    feature: BTreeMap<String, PixiFeature>,
+    #[serde(default)]
+    environments: BTreeMap<String, PixiEnvironment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PixiWorkspace {
+    #[serde(default)]
+    platforms: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PixiFeature {
+    platforms: Option<Vec<String>>,
+    #[serde(flatten)]
+    dependencies: PixiDependencies,
+    #[serde(default)]
+    target: BTreeMap<String, PixiDependencies>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PixiDependencies {
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+    #[serde(default, rename = "pypi-dependencies")]
+    pypi_dependencies: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum PixiEnvironment {
+    Features(Vec<String>),
+    Table {
+        #[serde(default)]
+        features: Vec<String>,
+        #[serde(default, rename = "no-default-feature")]
+        no_default_feature: bool,
+    },
+}
+
+impl Pixi {
+    const PACKAGE_KINDS: [&str; 2] = ["conda", "pypi"];
+    const DEFAULT_ENVIRONMENT: &str = "default";
+
+    /// Get the URL (or path) identifying a package entry in a pixi lockfile.
+    ///
+    /// * `entry`: A `- conda: <url>` or `- pypi: <url>` mapping
+    fn package_url(entry: &serde_yaml::Mapping) -> Option<&str> {
+        Self::PACKAGE_KINDS
+            .iter()
+            .find_map(|kind| entry.get(kind).and_then(|url| url.as_str()))
+    }
+
+    /// Render a pixi dependency value as a version spec.
+    ///
+    /// * `value`: Either a version string, or a table like `{ version = "...", channel = "..." }`
+    fn dependency_spec(value: &toml::Value) -> String {
+        match value {
+            toml::Value::String(spec) => spec.clone(),
+            toml::Value::Table(table) => match table.get("version") {
+                Some(toml::Value::String(spec)) => spec.clone(),
+                _ => table.to_string().trim().replace('\n', ", "),
+            },
+            other => other.to_string(),
+        }
+    }
+
+    /// Check whether a `[target.<selector>]` table applies to a platform.
+    ///
+    /// * `selector`: Either a platform like `linux-64`, or a family like `unix` or `osx`
+    /// * `platform`: Platform the environment is locked for
+    fn target_matches(selector: &str, platform: &str) -> bool {
+        match selector {
+            "unix" => !platform.starts_with("win"),
+            "linux" | "osx" | "win" => platform.starts_with(selector),
+            _ => selector == platform,
+        }
+    }
+}
+
+impl LockSpecFormat for Pixi {
+    fn name(&self) -> &'static str {
+        "pixi"
+    }
+
+    fn specfile_name(&self) -> &'static str {
+        "pixi.toml"
+    }
+
+    fn lockfile_name(&self) -> &'static str {
+        "pixi.lock"
+    }
+
+    fn install_command(&self) -> Command {
+        let mut command = Command::new("pixi");
+        command.args(["install", "--frozen", "--color", "always"]);
+        command
+    }
+
+    fn install_dirs(&self) -> &'static [&'static str] {
+        &[".pixi/"]
+    }
+
+    fn environment_args(&self, environment: &str) -> Result<Vec<String>, String> {
+        Ok(vec!["--environment".to_string(), environment.to_string()])
+    }
+
+    fn parse_packages(&self, lockfile: &str) -> Result<Vec<Package>, String> {
+        let lock: PixiLockfile = serde_yaml::from_str(lockfile)
+            .map_err(|err| format!("Unable to parse pixi lockfile: {err}"))?;
+
+        // Newer lockfiles omit the name and version of conda packages when they can be
+        // derived from the filename, so fall back to that when needed
+        let mut versions: HashMap<&str, (String, String)> = HashMap::new();
+        for entry in &lock.packages {
+            let Some(url) = Pixi::package_url(entry) else {
+                continue;
+            };
+            let field = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+            };
+            let name_version = match (field("name"), field("version")) {
+                (Some(name), Some(version)) => Some((name, version)),
+                _ => parse_conda_filename(url),
+            };
+            if let Some(name_version) = name_version {
+                versions.insert(url, name_version);
+            }
+        }
+
+        let mut packages = vec![];
+        for (environment, env) in &lock.environments {
+            for (platform, entries) in &env.packages {
+                for entry in entries {
+                    let url = Pixi::package_url(entry).ok_or(format!(
+                        "Unrecognized package entry in environment '{environment}': {entry:?}"
+                    ))?;
+                    let (name, version) = versions
+                        .get(url)
+                        .cloned()
+                        .ok_or(format!("Unable to determine the package name of {url}"))?;
+                    packages.push(Package {
+                        environment: environment.clone(),
+                        platform: platform.clone(),
+                        name,
+                        version,
+                    });
+                }
+            }
+        }
+        packages.sort();
+        Ok(packages)
+    }
+
+    fn parse_dependencies(&self, specfile: &str) -> Result<Vec<Dependency>, String> {
+        let manifest: PixiManifest = toml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse pixi manifest: {err}"))?;
+
+        let mut environments: BTreeMap<&str, (bool, &[String])> = manifest
+            .environments
+            .iter()
+            .map(|(name, env)| {
+                let resolved = match env {
+                    PixiEnvironment::Features(features) => (true, features.as_slice()),
+                    PixiEnvironment::Table {
+                        features,
+                        no_default_feature,
+                    } => (!no_default_feature, features.as_slice()),
+                };
+                (name.as_str(), resolved)
+            })
+            .collect();
+        environments
+            .entry(Self::DEFAULT_ENVIRONMENT)
+            .or_insert((true, &[]));
+
+        let mut dependencies = vec![];
+        for (environment, (use_default, feature_names)) in environments {
+            let mut features = vec![];
+            if use_default {
+                features.push(&manifest.default_feature);
+            }
+            for name in feature_names {
+                features.push(manifest.feature.get(name).ok_or(format!(
+                    "Environment '{environment}' uses undefined feature '{name}'"
+                ))?);
+            }
+
+            // Features may restrict the platforms an environment supports
+            let platforms = manifest.workspace.platforms.iter().filter(|platform| {
+                features.iter().all(|feature| {
+                    feature
+                        .platforms
+                        .as_ref()
+                        .is_none_or(|allowed| allowed.contains(platform))
+                })
+            });
+
+            for platform in platforms {
+                for feature in &features {
+                    let targets = feature
+                        .target
+                        .iter()
+                        .filter(|(selector, _)| Self::target_matches(selector, platform))
+                        .map(|(_, deps)| deps);
+                    for deps in std::iter::once(&feature.dependencies).chain(targets) {
+                        for (name, value) in deps.dependencies.iter().chain(&deps.pypi_dependencies)
+                        {
+                            dependencies.push(Dependency {
+                                environment: environment.to_string(),
+                                platform: Some(platform.clone()),
+                                name: name.clone(),
+                                spec: Self::dependency_spec(value),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        dependencies.sort();
+        Ok(dependencies)
+    }
+
+    fn parse_environments(&self, specfile: &str) -> Result<BTreeSet<String>, String> {
+        let manifest: PixiManifest = toml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse pixi manifest: {err}"))?;
+        let mut environments: BTreeSet<String> = manifest.environments.into_keys().collect();
+        environments.insert(Self::DEFAULT_ENVIRONMENT.to_string());
+        Ok(environments)
+    }
+
+    fn parse_locked_environments(&self, lockfile: &str) -> Result<BTreeSet<String>, String> {
+        let lock: PixiLockfile = serde_yaml::from_str(lockfile)
+            .map_err(|err| format!("Unable to parse pixi lockfile: {err}"))?;
+        Ok(lock.environments.into_keys().collect())
+    }
+
+    fn araki_config(&self, specfile: &str) -> Result<ArakiConfig, String> {
+        let toml_data: Table = specfile
+            .parse()
+            .map_err(|err| format!("Unable to parse specfile as valid toml.\nReason: {err}"))?;
+        match toml_data.get("araki") {
+            Some(araki) => araki
+                .clone()
+                .try_into()
+                .map_err(|err| format!("Invalid [araki] table: {err}")),
+            None => Ok(ArakiConfig::default()),
+        }
+    }
+
+    fn add_araki_metadata(
+        &self,
+        specfile: &str,
+        lockspec_name: &str,
+    ) -> Result<Option<String>, String> {
+        let mut toml_data: Table = specfile
+            .parse()
+            .map_err(|err| format!("Unable to parse specfile as valid toml.\nReason: {err}"))?;
+
+        if toml_data.get("araki").is_some() {
+            return Ok(None);
+        }
+        let mut araki_table = Table::new();
+        araki_table.insert("lockspec_name".to_string(), lockspec_name.into());
+        toml_data.insert("araki".to_string(), toml::Value::Table(araki_table));
+        Ok(Some(toml_data.to_string()))
+    }
+}
+
+/// Get the name and version of a conda package from its URL.
+///
+/// Conda package filenames have the form `<name>-<version>-<build>.conda` (or `.tar.bz2`).
+///
+/// * `url`: URL or path of the conda package
+fn parse_conda_filename(url: &str) -> Option<(String, String)> {
+    let filename = url.rsplit('/').next()?;
+    let stem = filename
+        .strip_suffix(".conda")
+        .or_else(|| filename.strip_suffix(".tar.bz2"))?;
+    let mut parts = stem.rsplitn(3, '-');
+    let _build = parts.next()?;
+    let version = parts.next()?;
+    let name = parts.next()?;
+    Some((name.to_string(), version.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conda_filename() {
+        assert_eq!(
+            parse_conda_filename(
+                "https://conda.anaconda.org/conda-forge/linux-64/_openmp_mutex-4.5-2_gnu.tar.bz2"
+            ),
+            Some(("_openmp_mutex".to_string(), "4.5".to_string()))
+        );
+        assert_eq!(
+            parse_conda_filename(
+                "https://conda.anaconda.org/conda-forge/noarch/libgcc-devel_linux-64-14.3.0-h85bb3a7_107.conda"
+            ),
+            Some(("libgcc-devel_linux-64".to_string(), "14.3.0".to_string()))
+        );
+        assert_eq!(
+            parse_conda_filename("https://files.pythonhosted.org/a.whl"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_packages() {
+        let lockfile = r#"
+version: 6
+environments:
+  default:
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/python-3.13.0-h9ebbce0_100_cp313.conda
+      - pypi: https://files.pythonhosted.org/packages/rich-13.9.4-py3-none-any.whl
+packages:
+- conda: https://conda.anaconda.org/conda-forge/linux-64/python-3.13.0-h9ebbce0_100_cp313.conda
+  sha256: 0000
+- pypi: https://files.pythonhosted.org/packages/rich-13.9.4-py3-none-any.whl
+  name: rich
+  version: 13.9.4
+"#;
+        let packages = Pixi
+            .parse_packages(lockfile)
+            .expect("should be able to parse the lockfile");
+        let names: Vec<(&str, &str)> = packages
+            .iter()
+            .map(|pkg| (pkg.name.as_str(), pkg.version.as_str()))
+            .collect();
+        assert_eq!(names, vec![("python", "3.13.0"), ("rich", "13.9.4")]);
+        assert!(packages.iter().all(|pkg| pkg.platform == "linux-64"));
+    }
+
+    #[test]
+    fn test_parse_dependencies() {
+        let manifest = r#"
+[workspace]
+name = "test"
+channels = ["conda-forge"]
+platforms = ["linux-64", "win-64"]
+
+[dependencies]
+python = ">=3.13"
+
+[target.unix.dependencies]
+readline = "*"
+
+[feature.test.dependencies]
+pytest = { version = "8.*", channel = "conda-forge" }
+
+[feature.test]
+platforms = ["linux-64"]
+
+[environments]
+test = ["test"]
+"#;
+        let dependencies = Pixi
+            .parse_dependencies(manifest)
+            .expect("should be able to parse the manifest");
+        let rendered: Vec<String> = dependencies
+            .iter()
+            .map(|dep| {
+                format!(
+                    "{}/{}/{} {}",
+                    dep.environment,
+                    dep.platform.clone().unwrap_or_default(),
+                    dep.name,
+                    dep.spec
+                )
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "default/linux-64/python >=3.13",
+                "default/linux-64/readline *",
+                "default/win-64/python >=3.13",
+                "test/linux-64/pytest 8.*",
+                "test/linux-64/python >=3.13",
+                "test/linux-64/readline *",
+            ]
+        );
+    }
+}
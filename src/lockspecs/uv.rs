@@ -0,0 +1,196 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::process::Command;
+use toml::Table;
+
+use super::{Dependency, LockSpecFormat, Package, parse_requirement};
+use crate::config::ArakiConfig;
+
+/// Lockspecs managed by uv: `pyproject.toml` and `uv.lock`.
+#[derive(Debug)]
+pub struct Uv;
+
+#[derive(Deserialize, Debug)]
+struct UvLockfile {
+    #[serde(default)]
+    package: Vec<UvLockPackage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvLockPackage {
+    name: String,
+    // Packages with a dynamic version have no version in the lockfile
+    #[serde(default)]
+    version: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PyProject {
+    project: Option<PyProjectMetadata>,
+    #[serde(default, rename = "dependency-groups")]
+    dependency_groups: BTreeMap<String, Vec<toml::Value>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PyProjectMetadata {
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default, rename = "optional-dependencies")]
+    optional_dependencies: BTreeMap<String, Vec<String>>,
+}
+
+impl Uv {
+    /// uv lockfiles are universal: a single resolution covers every platform.
+    const PLATFORM: &str = "universal";
+    const ENVIRONMENT: &str = "default";
+}
+
+impl LockSpecFormat for Uv {
+    fn name(&self) -> &'static str {
+        "uv"
+    }
+
+    fn specfile_name(&self) -> &'static str {
+        "pyproject.toml"
+    }
+
+    fn lockfile_name(&self) -> &'static str {
+        "uv.lock"
+    }
+
+    fn install_command(&self) -> Command {
+        let mut command = Command::new("uv");
+        command.args(["sync", "--frozen"]);
+        command
+    }
+
+    fn install_dirs(&self) -> &'static [&'static str] {
+        &[".venv/"]
+    }
+
+    fn parse_packages(&self, lockfile: &str) -> Result<Vec<Package>, String> {
+        let lock: UvLockfile = toml::from_str(lockfile)
+            .map_err(|err| format!("Unable to parse uv lockfile: {err}"))?;
+
+        let mut packages: Vec<Package> = lock
+            .package
+            .into_iter()
+            .map(|pkg| Package {
+                environment: Self::ENVIRONMENT.to_string(),
+                platform: Self::PLATFORM.to_string(),
+                name: pkg.name,
+                version: pkg.version,
+            })
+            .collect();
+        packages.sort();
+        Ok(packages)
+    }
+
+    fn parse_dependencies(&self, specfile: &str) -> Result<Vec<Dependency>, String> {
+        let pyproject: PyProject = toml::from_str(specfile)
+            .map_err(|err| format!("Unable to parse pyproject.toml: {err}"))?;
+
+        let mut requirements: Vec<&String> = vec![];
+        if let Some(project) = &pyproject.project {
+            requirements.extend(&project.dependencies);
+            requirements.extend(project.optional_dependencies.values().flatten());
+        }
+        // Groups may also contain `{ include-group = "..." }` tables, which aren't requirements
+        requirements.extend(
+            pyproject
+                .dependency_groups
+                .values()
+                .flatten()
+                .filter_map(|item| match item {
+                    toml::Value::String(requirement) => Some(requirement),
+                    _ => None,
+                }),
+        );
+
+        let mut dependencies: Vec<Dependency> = requirements
+            .into_iter()
+            .map(|requirement| {
+                let (name, spec) = parse_requirement(requirement);
+                Dependency {
+                    environment: Self::ENVIRONMENT.to_string(),
+                    platform: None,
+                    name,
+                    spec,
+                }
+            })
+            .collect();
+        dependencies.sort();
+        Ok(dependencies)
+    }
+
+    fn araki_config(&self, specfile: &str) -> Result<ArakiConfig, String> {
+        let toml_data: Table = specfile
+            .parse()
+            .map_err(|err| format!("Unable to parse specfile as valid toml.\nReason: {err}"))?;
+        match toml_data.get("tool").and_then(|tool| tool.get("araki")) {
+            Some(araki) => araki
+                .clone()
+                .try_into()
+                .map_err(|err| format!("Invalid [tool.araki] table: {err}")),
+            None => Ok(ArakiConfig::default()),
+        }
+    }
+
+    fn add_araki_metadata(
+        &self,
+        specfile: &str,
+        lockspec_name: &str,
+    ) -> Result<Option<String>, String> {
+        let mut toml_data: Table = specfile
+            .parse()
+            .map_err(|err| format!("Unable to parse specfile as valid toml.\nReason: {err}"))?;
+
+        // Third-party tools are expected to keep their configuration under `[tool]`
+        let tool = toml_data
+            .entry("tool")
+            .or_insert_with(|| toml::Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or("The `tool` key of the specfile is not a table.")?;
+
+        if tool.get("araki").is_some() {
+            return Ok(None);
+        }
+        let mut araki_table = Table::new();
+        araki_table.insert("lockspec_name".to_string(), lockspec_name.into());
+        tool.insert("araki".to_string(), toml::Value::Table(araki_table));
+        Ok(Some(toml_data.to_string()))
+    }
+
+    fn exempt_tools(&self) -> &'static [&'static str] {
+        &["uv"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_packages() {
+        let lockfile = r#"
+version = 1
+requires-python = ">=3.12"
+
+[[package]]
+name = "myproject"
+source = { editable = "." }
+
+[[package]]
+name = "requests"
+version = "2.32.3"
+source = { registry = "https://pypi.org/simple" }
+"#;
+        let packages = Uv
+            .parse_packages(lockfile)
+            .expect("should be able to parse the lockfile");
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[1].name, "requests");
+        assert_eq!(packages[1].version, "2.32.3");
+        assert_eq!(packages[1].platform, "universal");
+    }
+}
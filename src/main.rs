@@ -1,19 +1,31 @@
-use clap::{Parser, Subcommand};
+use araki::ArakiError;
+use araki::registry::Registry;
+use clap::{ArgAction, Parser, Subcommand};
+use console::style;
+use log::debug;
+use std::env::set_current_dir;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 
 use crate::cli::auth;
+use crate::cli::check;
 use crate::cli::checkout;
 use crate::cli::clone;
+use crate::cli::diff;
+use crate::cli::envs;
 use crate::cli::init;
 use crate::cli::list;
+use crate::cli::logging;
+use crate::cli::output::OutputFormat;
 use crate::cli::pull;
 use crate::cli::push;
 use crate::cli::shell;
 use crate::cli::shim;
+use crate::cli::stash;
+use crate::cli::status;
 use crate::cli::tag;
 
-pub mod backends;
-pub mod cli;
-pub mod common;
+mod cli;
 
 /// Manage and share environments
 #[derive(Parser, Debug)]
@@ -22,6 +34,36 @@ pub struct Cli {
     // Manage environments
     #[command(subcommand)]
     command: Option<Command>,
+
+    /// Format of the results printed by read commands
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Log more details; repeat for even more (-vv)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Write the log to a file instead of stderr
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Run as if araki was started in the directory of this environment, given by its name
+    /// (see `araki envs ls`) or its path
+    #[arg(long, global = true, value_name = "NAME|PATH")]
+    env: Option<String>,
+
+    /// Run as if araki was started in this directory
+    #[arg(
+        short = 'C',
+        global = true,
+        value_name = "PATH",
+        conflicts_with = "env"
+    )]
+    directory: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -30,12 +72,21 @@ pub enum Command {
     /// Authenticate with the configured backend
     Auth(auth::Args),
 
+    /// Check that the lockfile is consistent with the specfile
+    Check(check::Args),
+
     /// Checkout a tag of an environment
     Checkout(checkout::Args),
 
     /// Clone a lockspec from a remote repository and install it in the current directory
     Clone(clone::Args),
 
+    /// Show the dependency and package changes between two revisions of the lockspec
+    Diff(diff::Args),
+
+    /// List the environments managed by araki
+    Envs(envs::Args),
+
     /// Create a new araki-managed lockspec from an existing lockspec
     Init(init::Args),
 
@@ -56,6 +107,12 @@ pub enum Command {
     #[command(hide = true)]
     Shim(shim::Args),
 
+    /// Restore lockspec changes saved with `araki checkout --stash`
+    Stash(stash::Args),
+
+    /// Show the changes to the lockspec since the last tag
+    Status(status::Args),
+
     /// Save the current version of the environment
     Tag(tag::Args),
 }
@@ -63,21 +120,125 @@ pub enum Command {
 #[tokio::main]
 pub async fn main() {
     let cli = Cli::parse();
+    if let Err(err) = logging::init(cli.verbose, cli.quiet, cli.log_file.as_deref()) {
+        report(&err);
+        exit(exit_code(&err));
+    }
+
+    if let Err(err) = change_directory(cli.env.as_deref(), cli.directory.as_deref()) {
+        report(&err);
+        exit(exit_code(&err));
+    }
 
     if let Some(cmd) = cli.command {
-        match cmd {
-            Command::Auth(cmd) => auth::execute(cmd).await,
+        let result = match cmd {
+            Command::Auth(cmd) => auth::execute(cmd, cli.output).await,
+            Command::Check(cmd) => check::execute(cmd),
             Command::Checkout(cmd) => checkout::execute(cmd),
             Command::Clone(cmd) => clone::execute(cmd),
+            Command::Diff(cmd) => diff::execute(cmd, cli.output),
+            Command::Envs(cmd) => envs::execute(cmd, cli.output),
             Command::Init(cmd) => init::execute(cmd).await,
-            Command::List(cmd) => list::execute(cmd),
+            Command::List(cmd) => list::execute(cmd, cli.output),
             Command::Pull(cmd) => pull::execute(cmd),
             Command::Push(cmd) => push::execute(cmd),
             Command::Shell(cmd) => shell::execute(cmd),
             Command::Shim(cmd) => shim::execute(cmd),
-            Command::Tag(cmd) => tag::execute(cmd),
+            Command::Stash(cmd) => stash::execute(cmd),
+            Command::Status(cmd) => status::execute(cmd, cli.output),
+            Command::Tag(cmd) => tag::execute(cmd).await,
+        };
+        if let Err(err) = result {
+            report(&err);
+            exit(exit_code(&err));
         }
     } else {
-        std::process::exit(2);
+        exit(2);
+    }
+}
+
+/// Change to the directory of the environment selected with `--env` or `-C`, so that every
+/// command runs as if araki was started there.
+///
+/// * `env`: Name or path of an environment
+/// * `directory`: Directory to change to
+fn change_directory(env: Option<&str>, directory: Option<&Path>) -> Result<(), ArakiError> {
+    let target = match (env, directory) {
+        (Some(env), _) => {
+            let registry = Registry::load()?;
+            match registry.find(env).as_slice() {
+                [entry] => entry.path.clone(),
+                [] if Path::new(env).is_dir() => PathBuf::from(env),
+                [] => return Err(ArakiError::UnknownEnv(env.to_string())),
+                entries => {
+                    return Err(ArakiError::AmbiguousEnv {
+                        name: env.to_string(),
+                        paths: entries.iter().map(|entry| entry.path.clone()).collect(),
+                    });
+                }
+            }
+        }
+        (None, Some(directory)) => directory.to_path_buf(),
+        (None, None) => return Ok(()),
+    };
+    debug!(directory:? = target; "Changing directory");
+    set_current_dir(&target).map_err(ArakiError::io(format!(
+        "Unable to change to the directory {target:?}"
+    )))
+}
+
+/// Print an error, along with every problem found if it's a failed lockspec check, or every
+/// changed file if the lockspec has uncommitted changes.
+///
+/// * `err`: Error returned by a command
+fn report(err: &ArakiError) {
+    match err {
+        ArakiError::Inconsistent(problems) => {
+            for problem in problems {
+                eprintln!("{} {problem}", style("✗").red().bold());
+            }
+        }
+        ArakiError::DirtyWorkspace(changes) => {
+            for change in changes {
+                let status = format!("{}:", change.status);
+                eprintln!(
+                    "  {} {}",
+                    style(format!("{status:<10}")).yellow(),
+                    change.path.display()
+                );
+            }
+        }
+        _ => {}
+    }
+    eprintln!("{err}");
+}
+
+/// Get the exit code araki should exit with after an error.
+///
+/// * `err`: Error returned by a command
+fn exit_code(err: &ArakiError) -> i32 {
+    match err {
+        ArakiError::Inconsistent(_) => 3,
+        ArakiError::NotManaged(_)
+        | ArakiError::NoLockSpec(_)
+        | ArakiError::RevisionNotFound(_)
+        | ArakiError::UnknownEnv(_) => 4,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positional_args_are_not_global_options() {
+        // A positional arg sharing the id of a global option, e.g. `env`, is read as the option
+        let cli = Cli::try_parse_from(["araki", "clone", "org/repo"]).unwrap();
+        assert_eq!(cli.env, None);
+        assert!(matches!(cli.command, Some(Command::Clone(_))));
+
+        let cli = Cli::try_parse_from(["araki", "list", "org/repo", "--remote"]).unwrap();
+        assert_eq!(cli.env, None);
     }
 }
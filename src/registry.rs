@@ -0,0 +1,142 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::common::{ARAKI_GIT_DIR_NAME, get_araki_dir};
+use crate::error::ArakiError;
+
+const REGISTRY_FILE_NAME: &str = "envs.json";
+
+/// An environment managed by araki, recorded when it is initialized or cloned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnvEntry {
+    /// Name of the lockspec repository
+    pub name: String,
+    /// Directory containing the lockspec
+    pub path: PathBuf,
+    /// URL of the lockspec repository
+    pub remote: Option<String>,
+}
+
+impl EnvEntry {
+    /// Check whether the environment is still managed by araki at its recorded path.
+    pub fn exists(&self) -> bool {
+        self.path.join(ARAKI_GIT_DIR_NAME).exists()
+    }
+}
+
+/// The environments known to araki, stored in `~/.araki/envs.json`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Registry {
+    #[serde(default)]
+    envs: Vec<EnvEntry>,
+}
+
+impl Registry {
+    /// Get the path to the registry file.
+    pub fn path() -> Result<PathBuf, ArakiError> {
+        Ok(get_araki_dir()?.join(REGISTRY_FILE_NAME))
+    }
+
+    /// Load the registry, or an empty registry if none has been written yet.
+    pub fn load() -> Result<Registry, ArakiError> {
+        Self::load_from(&Self::path()?)
+    }
+
+    /// Load a registry from a file, or an empty registry if the file doesn't exist.
+    ///
+    /// * `path`: Registry file
+    pub fn load_from(path: &Path) -> Result<Registry, ArakiError> {
+        if !path.exists() {
+            return Ok(Registry::default());
+        }
+        let contents =
+            fs::read_to_string(path).map_err(ArakiError::io(format!("Unable to read {path:?}")))?;
+        serde_json::from_str(&contents)
+            .map_err(|err| ArakiError::Other(format!("Unable to parse {path:?}: {err}")))
+    }
+
+    /// Write the registry to its file.
+    pub fn save(&self) -> Result<(), ArakiError> {
+        self.save_to(&Self::path()?)
+    }
+
+    /// Write the registry to a file.
+    ///
+    /// * `path`: Registry file
+    pub fn save_to(&self, path: &Path) -> Result<(), ArakiError> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|err| ArakiError::Other(format!("Unable to serialize the registry: {err}")))?;
+        fs::write(path, contents).map_err(ArakiError::io(format!("Unable to write {path:?}")))
+    }
+
+    pub fn envs(&self) -> &[EnvEntry] {
+        &self.envs
+    }
+
+    /// Find the environments recorded with a name. Several environments may share a name,
+    /// e.g. clones of the same lockspec repository.
+    ///
+    /// * `name`: Name of the lockspec repository
+    pub fn find(&self, name: &str) -> Vec<&EnvEntry> {
+        self.envs.iter().filter(|env| env.name == name).collect()
+    }
+
+    /// Record an environment, replacing any environment previously recorded at the same path.
+    ///
+    /// * `entry`: Environment to record
+    pub fn register(&mut self, entry: EnvEntry) {
+        self.envs.retain(|env| env.path != entry.path);
+        self.envs.push(entry);
+        self.envs
+            .sort_by(|a, b| a.name.cmp(&b.name).then(a.path.cmp(&b.path)));
+    }
+}
+
+/// Record a newly initialized or cloned environment in the registry.
+///
+/// * `name`: Name of the lockspec repository
+/// * `path`: Directory containing the lockspec
+/// * `remote`: URL of the lockspec repository
+pub fn register(name: &str, path: &Path, remote: Option<String>) -> Result<(), ArakiError> {
+    debug!(name = name, path:? = path; "Registering environment");
+    let mut registry = Registry::load()?;
+    registry.register(EnvEntry {
+        name: name.to_string(),
+        path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+        remote,
+    });
+    registry.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_register_replaces_same_path() {
+        let file = temp_dir().join(format!("araki-test-{}.json", Uuid::new_v4()));
+        let mut registry = Registry::load_from(&file).unwrap();
+        assert!(registry.envs().is_empty());
+
+        registry.register(EnvEntry {
+            name: "old".to_string(),
+            path: PathBuf::from("/envs/a"),
+            remote: None,
+        });
+        registry.register(EnvEntry {
+            name: "new".to_string(),
+            path: PathBuf::from("/envs/a"),
+            remote: Some("https://github.com/org/new".to_string()),
+        });
+        registry.save_to(&file).unwrap();
+
+        let registry = Registry::load_from(&file).unwrap();
+        assert_eq!(registry.envs().len(), 1);
+        assert_eq!(registry.envs()[0].name, "new");
+        fs::remove_file(file).unwrap();
+    }
+}
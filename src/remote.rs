@@ -0,0 +1,113 @@
+use regex::Regex;
+use std::fmt::Display;
+
+#[derive(Debug, Default)]
+pub struct RemoteRepo {
+    org: Option<String>,
+    repo: String,
+    domain: Option<String>,
+    protocol: Option<String>,
+}
+
+impl RemoteRepo {
+    pub fn new(
+        org: Option<String>,
+        repo: String,
+        domain: Option<String>,
+        protocol: Option<String>,
+    ) -> RemoteRepo {
+        RemoteRepo {
+            org,
+            repo,
+            domain,
+            protocol,
+        }
+    }
+    /// Render the repository as a git url
+    pub fn as_url(&self) -> String {
+        format!(
+            "{}{}/{}/{}",
+            self.get_protocol(),
+            self.get_domain(),
+            self.get_org(),
+            self.get_repo(),
+        )
+    }
+
+    /// Render the repository as an ssh URL
+    pub fn as_ssh_url(&self) -> String {
+        format!(
+            "git@{}:{}/{}.git",
+            self.get_domain(),
+            self.get_org(),
+            self.get_repo(),
+        )
+    }
+
+    pub fn get_org(&self) -> String {
+        self.org.clone().unwrap_or("nos-environments".into())
+    }
+    pub fn get_repo(&self) -> String {
+        self.repo.clone()
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone().unwrap_or("https://".into())
+    }
+    fn get_domain(&self) -> String {
+        self.domain.clone().unwrap_or("github.com".into())
+    }
+}
+
+impl Display for RemoteRepo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_url())
+    }
+}
+
+/// Parse the name or URL of a lockspec repository.
+///
+/// * `env`: Remote URL for an lockspec. If only <org>/<repo> is passed, the repository is
+///   assumed to live on github.
+pub fn parse_repo_arg(env: &str) -> Result<RemoteRepo, String> {
+    let re = Regex::new(
+        r"((?<protocol>(git\+)?https?://)?(?<domain>github\.com)/)?((?<org>[-a-zA-Z0-9_.]{1,100})/)?(?<repo>[-a-zA-Z0-9_.]{1,100}$)",
+    )
+    .map_err(|_| "Invalid regex for processing git url.")?;
+
+    let captures = re
+        .captures(env)
+        .ok_or(format!("Unrecognized format for repo name or URL: {env}."))?;
+
+    Ok(RemoteRepo::new(
+        captures.name("org").map(|name| name.as_str().to_string()),
+        captures
+            .name("repo")
+            .ok_or(format!("No repo name found in {env}"))?
+            .as_str()
+            .to_string(),
+        captures
+            .name("domain")
+            .map(|name| name.as_str().to_string()),
+        captures
+            .name("protocol")
+            .map(|name| name.as_str().to_string()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repo_arg() {
+        let remote = parse_repo_arg("my-env").expect("should parse a bare name");
+        assert_eq!(
+            remote.as_ssh_url(),
+            "git@github.com:nos-environments/my-env.git"
+        );
+
+        let remote = parse_repo_arg("https://github.com/org/my-env").expect("should parse a URL");
+        assert_eq!(remote.as_url(), "https://github.com/org/my-env");
+        assert_eq!(remote.as_ssh_url(), "git@github.com:org/my-env.git");
+    }
+}
@@ -0,0 +1,381 @@
+use git2::{ObjectType, Oid, Repository, Signature};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::env::temp_dir;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use uuid::Uuid;
+
+use crate::config::{SigningConfig, expand_home};
+use crate::error::ArakiError;
+
+const SSH_SIGNATURE_HEADER: &str = "-----BEGIN SSH SIGNATURE-----";
+const PGP_SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+
+/// Namespace of SSH signatures, shared with git so that `git tag -v` can verify araki's tags.
+const SSH_NAMESPACE: &str = "git";
+
+/// Kind of signature created for tags.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureFormat {
+    /// Sign with `ssh-keygen -Y sign`
+    #[default]
+    Ssh,
+    /// Sign with `gpg`
+    Gpg,
+}
+
+/// Result of verifying the signature of a tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The tag is lightweight or has no signature
+    Unsigned,
+    /// The tag has a valid signature from a trusted signer
+    Trusted { signer: String },
+    /// The tag has a signature, but it is invalid or its signer isn't trusted
+    Untrusted { reason: String },
+}
+
+/// Run a signing tool, feeding `input` on stdin, and return its stdout if it succeeds.
+///
+/// * `command`: Command to run
+/// * `input`: Data written to the standard input of the command
+fn run_with_input(command: &mut Command, input: &[u8]) -> Result<String, ArakiError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    debug!(command:? = command; "Running signing tool");
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ArakiError::io(format!("Unable to run {program}")))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input)
+            .map_err(ArakiError::io(format!("Unable to write to {program}")))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(ArakiError::io(format!("Unable to run {program}")))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        return Ok(stdout);
+    }
+    // ssh-keygen reports some failures on stdout
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    Err(ArakiError::Signing(format!(
+        "{program} exited with {}: {}",
+        output.status,
+        message.trim_end_matches('.').replace('\n', " ")
+    )))
+}
+
+/// Get the key to sign with, failing if none is configured.
+///
+/// * `config`: Signing configuration of the user
+pub fn signing_key(config: &SigningConfig) -> Result<&str, ArakiError> {
+    config.key.as_deref().ok_or(ArakiError::Signing(
+        "No signing key configured: set `key` in the [signing] table of ~/.araki/config.toml"
+            .to_string(),
+    ))
+}
+
+/// Sign a payload with the configured key, returning an armored signature.
+///
+/// * `payload`: Data to sign
+/// * `config`: Signing configuration of the user
+pub fn sign(payload: &[u8], config: &SigningConfig) -> Result<String, ArakiError> {
+    let key = signing_key(config)?;
+    let mut command = match config.format {
+        SignatureFormat::Ssh => {
+            let mut command = Command::new("ssh-keygen");
+            command
+                .args(["-Y", "sign", "-n", SSH_NAMESPACE, "-f"])
+                .arg(expand_home(Path::new(key)));
+            command
+        }
+        SignatureFormat::Gpg => {
+            let mut command = Command::new("gpg");
+            command.args(["--batch", "--armor", "--detach-sign", "--local-user", key]);
+            command
+        }
+    };
+    run_with_input(&mut command, payload)
+}
+
+/// Create an annotated tag signed with the configured key, in the same format as `git tag -s`.
+///
+/// * `repo`: araki git repository
+/// * `name`: Name of the tag
+/// * `target`: Commit to tag
+/// * `tagger`: Tagger of the tag
+/// * `message`: Message of the tag
+/// * `force`: Overwrite an existing tag of the same name
+/// * `config`: Signing configuration of the user
+pub fn create_signed_tag(
+    repo: &Repository,
+    name: &str,
+    target: Oid,
+    tagger: &Signature,
+    message: &str,
+    force: bool,
+    config: &SigningConfig,
+) -> Result<Oid, ArakiError> {
+    let when = tagger.when();
+    let offset = when.offset_minutes().abs();
+    let mut payload = format!(
+        "object {target}\ntype commit\ntag {name}\ntagger {} <{}> {} {}{:02}{:02}\n\n{}\n",
+        String::from_utf8_lossy(tagger.name_bytes()),
+        String::from_utf8_lossy(tagger.email_bytes()),
+        when.seconds(),
+        when.sign(),
+        offset / 60,
+        offset % 60,
+        message.trim_end()
+    );
+    let signature = sign(payload.as_bytes(), config)?;
+    payload.push_str(&signature);
+
+    let tag_oid = repo
+        .odb()
+        .and_then(|odb| odb.write(ObjectType::Tag, payload.as_bytes()))
+        .map_err(ArakiError::git("Unable to write the signed tag"))?;
+    repo.reference(
+        &format!("refs/tags/{name}"),
+        tag_oid,
+        force,
+        &format!("araki: signed tag {name}"),
+    )
+    .map_err(ArakiError::git("Unable to tag"))?;
+    Ok(tag_oid)
+}
+
+/// Verify the signature of a tag if the user trusts any signer, enforcing
+/// `require_signed_tags`. Untrusted signatures only cause a warning unless it is set.
+///
+/// * `repo`: araki git repository
+/// * `name`: Name of the tag
+/// * `config`: Signing configuration of the user
+pub fn check_tag(repo: &Repository, name: &str, config: &SigningConfig) -> Result<(), ArakiError> {
+    if !config.require_signed_tags && !config.has_trusted_signers() {
+        return Ok(());
+    }
+    let reason = match verify_tag(repo, name, config)? {
+        Verification::Trusted { signer } => {
+            info!(tag = name, signer = signer; "Good signature");
+            return Ok(());
+        }
+        Verification::Unsigned => "the tag is not signed".to_string(),
+        Verification::Untrusted { reason } => reason,
+    };
+    if config.require_signed_tags {
+        Err(ArakiError::UntrustedTag {
+            revision: format!("tag '{name}'"),
+            reason,
+        })
+    } else {
+        warn!(tag = name; "Tag has no trusted signature: {reason}");
+        Ok(())
+    }
+}
+
+/// Check that a commit has a tag with a trusted signature if the user requires it, e.g. the
+/// commit a clone checked out.
+///
+/// * `repo`: araki git repository
+/// * `commit`: Commit which must be tagged
+/// * `config`: Signing configuration of the user
+pub fn check_commit(
+    repo: &Repository,
+    commit: Oid,
+    config: &SigningConfig,
+) -> Result<(), ArakiError> {
+    if !config.require_signed_tags {
+        return Ok(());
+    }
+    let names = repo
+        .tag_names(None)
+        .map_err(ArakiError::git("Unable to list tags"))?;
+    let mut reasons = vec![];
+    for name in names.iter().flatten().flatten() {
+        let Ok(tagged) = repo
+            .revparse_single(&format!("refs/tags/{name}"))
+            .and_then(|obj| obj.peel_to_commit())
+        else {
+            continue;
+        };
+        if tagged.id() != commit {
+            continue;
+        }
+        match verify_tag(repo, name, config)? {
+            Verification::Trusted { signer } => {
+                info!(tag = name, signer = signer; "Good signature");
+                return Ok(());
+            }
+            Verification::Unsigned => reasons.push(format!("tag '{name}' is not signed")),
+            Verification::Untrusted { reason } => reasons.push(format!("tag '{name}': {reason}")),
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push("it has no tag".to_string());
+    }
+    Err(ArakiError::UntrustedTag {
+        revision: format!("commit {commit}"),
+        reason: reasons.join("; "),
+    })
+}
+
+/// Split the raw contents of a tag object into the signed payload and the signature.
+///
+/// * `contents`: Raw contents of the tag object
+fn split_signature(contents: &str) -> Option<(&str, &str)> {
+    [SSH_SIGNATURE_HEADER, PGP_SIGNATURE_HEADER]
+        .iter()
+        .filter_map(|header| contents.find(&format!("\n{header}")))
+        .min()
+        .map(|start| contents.split_at(start + 1))
+}
+
+/// Verify the signature of a tag against the signers trusted by the user.
+///
+/// * `repo`: araki git repository
+/// * `name`: Name of the tag
+/// * `config`: Signing configuration of the user
+pub fn verify_tag(
+    repo: &Repository,
+    name: &str,
+    config: &SigningConfig,
+) -> Result<Verification, ArakiError> {
+    let tag_ref = repo
+        .find_reference(&format!("refs/tags/{name}"))
+        .map_err(|_| ArakiError::RevisionNotFound(name.to_string()))?;
+    let Ok(tag) = tag_ref.peel_to_tag() else {
+        return Ok(Verification::Unsigned);
+    };
+    let contents = repo
+        .odb()
+        .and_then(|odb| Ok(String::from_utf8_lossy(odb.read(tag.id())?.data()).to_string()))
+        .map_err(ArakiError::git(format!("Unable to read tag {name}")))?;
+    let Some((payload, signature)) = split_signature(&contents) else {
+        return Ok(Verification::Unsigned);
+    };
+
+    // The signature has to be read from a file; the payload is passed on stdin
+    let signature_file = temp_dir().join(format!("araki-{}.sig", Uuid::new_v4()));
+    fs::write(&signature_file, signature).map_err(ArakiError::io(format!(
+        "Unable to write the signature of {name} to {signature_file:?}"
+    )))?;
+    let tagger = tag
+        .tagger()
+        .map(|tagger| String::from_utf8_lossy(tagger.email_bytes()).to_string())
+        .unwrap_or_default();
+    let result = if signature.starts_with(SSH_SIGNATURE_HEADER) {
+        verify_ssh(payload, &signature_file, &tagger, config)
+    } else {
+        verify_gpg(payload, &signature_file, config)
+    };
+    let _ = fs::remove_file(&signature_file);
+    result
+}
+
+/// Verify an SSH signature against the allowed signers file of the user.
+///
+/// * `payload`: Signed data
+/// * `signature_file`: File containing the signature
+/// * `principal`: Email of the tagger, which must be allowed to sign with the key
+/// * `config`: Signing configuration of the user
+fn verify_ssh(
+    payload: &str,
+    signature_file: &Path,
+    principal: &str,
+    config: &SigningConfig,
+) -> Result<Verification, ArakiError> {
+    let Some(allowed_signers) = &config.allowed_signers else {
+        return Ok(Verification::Untrusted {
+            reason: "signed with SSH, but no `allowed_signers` file is configured".to_string(),
+        });
+    };
+    let mut command = Command::new("ssh-keygen");
+    command
+        .args(["-Y", "verify", "-n", SSH_NAMESPACE, "-f"])
+        .arg(allowed_signers)
+        .args(["-I", principal, "-s"])
+        .arg(signature_file);
+    match run_with_input(&mut command, payload.as_bytes()) {
+        Ok(_) => Ok(Verification::Trusted {
+            signer: principal.to_string(),
+        }),
+        Err(ArakiError::Signing(reason)) => Ok(Verification::Untrusted { reason }),
+        Err(err) => Err(err),
+    }
+}
+
+/// Verify a GPG signature, and check that the key which made it is trusted by the user.
+///
+/// * `payload`: Signed data
+/// * `signature_file`: File containing the signature
+/// * `config`: Signing configuration of the user
+fn verify_gpg(
+    payload: &str,
+    signature_file: &Path,
+    config: &SigningConfig,
+) -> Result<Verification, ArakiError> {
+    let mut command = Command::new("gpg");
+    command
+        .args(["--batch", "--status-fd=1", "--verify"])
+        .arg(signature_file)
+        .arg("-");
+    let status = match run_with_input(&mut command, payload.as_bytes()) {
+        Ok(status) => status,
+        Err(ArakiError::Signing(reason)) => return Ok(Verification::Untrusted { reason }),
+        Err(err) => return Err(err),
+    };
+    // e.g. `[GNUPG:] VALIDSIG <fingerprint> <date> ... <primary key fingerprint>`
+    let Some(fingerprints) = status
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .map(|rest| rest.split_whitespace().collect::<Vec<_>>())
+    else {
+        return Ok(Verification::Untrusted {
+            reason: "gpg did not report a valid signature".to_string(),
+        });
+    };
+    let signing_key = fingerprints.first().copied().unwrap_or_default();
+    let primary_key = fingerprints.last().copied().unwrap_or_default();
+    let trusted = config.trusted_keys.iter().any(|trusted| {
+        let trusted = trusted.replace(' ', "").to_uppercase();
+        !trusted.is_empty() && (signing_key.ends_with(&trusted) || primary_key.ends_with(&trusted))
+    });
+    if trusted {
+        Ok(Verification::Trusted {
+            signer: primary_key.to_string(),
+        })
+    } else {
+        Ok(Verification::Untrusted {
+            reason: format!("key {primary_key} is not in `trusted_keys`"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_signature() {
+        let contents = "object 0123\ntype commit\ntag v1\ntagger A <a@b> 0 +0000\n\nmessage\n\
+            -----BEGIN SSH SIGNATURE-----\nabc\n-----END SSH SIGNATURE-----\n";
+        let (payload, signature) = split_signature(contents).unwrap();
+        assert!(payload.ends_with("message\n"));
+        assert!(signature.starts_with(SSH_SIGNATURE_HEADER));
+        assert!(split_signature("object 0123\n\nmessage\n").is_none());
+    }
+}
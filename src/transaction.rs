@@ -0,0 +1,366 @@
+use log::debug;
+use std::env::temp_dir;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::common::{CopyStrategy, copy_fs_obj, copy_metadata};
+
+/// A filesystem change made as part of a transaction, recorded so that it can be undone.
+#[derive(Debug)]
+enum Action {
+    /// A file or directory which didn't exist before
+    Created(PathBuf),
+    /// A file which existed before; its original contents are saved at `backup`
+    Overwritten { path: PathBuf, backup: PathBuf },
+    /// A file which existed before and was appended to
+    Appended { path: PathBuf, original_len: u64 },
+}
+
+/// Records every file created, overwritten or appended while materializing a workspace, so that
+/// the original state of the filesystem can be restored if anything goes wrong.
+///
+/// Changes are undone by `rollback`, or when the transaction is dropped without being committed.
+/// Note that `std::process::exit` doesn't run destructors, so roll back explicitly before exiting.
+#[derive(Debug)]
+pub struct Transaction {
+    journal: Vec<Action>,
+    backup_dir: PathBuf,
+    finished: bool,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Transaction {
+            journal: vec![],
+            backup_dir: temp_dir().join(format!("araki-transaction-{}", Uuid::new_v4())),
+            finished: false,
+        }
+    }
+
+    /// Create a directory and any missing parents.
+    ///
+    /// * `path`: Directory to create
+    pub fn create_dir_all(&mut self, path: &Path) -> Result<(), String> {
+        // Only the outermost missing directory needs to be recorded; removing it removes the rest
+        let missing = path.ancestors().take_while(|dir| !dir.exists()).last();
+        fs::create_dir_all(path).map_err(|err| format!("Unable to create {path:?}: {err}"))?;
+        if let Some(dir) = missing {
+            self.journal.push(Action::Created(dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Save a copy of a file which is about to be overwritten.
+    ///
+    /// * `path`: File which is about to be overwritten
+    fn backup(&mut self, path: &Path) -> Result<(), String> {
+        fs::create_dir_all(&self.backup_dir)
+            .map_err(|err| format!("Unable to create {:?}: {err}", self.backup_dir))?;
+        let backup = self.backup_dir.join(self.journal.len().to_string());
+        copy_fs_obj(path, &backup, CopyStrategy::Reflink)
+            .map_err(|err| format!("Unable to back up {path:?}: {err}"))?;
+        self.journal.push(Action::Overwritten {
+            path: path.to_path_buf(),
+            backup,
+        });
+        Ok(())
+    }
+
+    /// Copy a file or symlink, backing up the destination first if it exists.
+    ///
+    /// * `from`: File to copy
+    /// * `to`: Destination of the copy
+    /// * `strategy`: How the file contents should be transferred
+    pub fn copy_file(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        strategy: CopyStrategy,
+    ) -> Result<(), String> {
+        if fs::symlink_metadata(to).is_ok() {
+            self.backup(to)?;
+        } else {
+            self.journal.push(Action::Created(to.to_path_buf()));
+        }
+        copy_fs_obj(from, to, strategy).map_err(|err| err.to_string())
+    }
+
+    /// Append to a file, creating it if it doesn't exist.
+    ///
+    /// * `path`: File to append to
+    /// * `contents`: Contents to append
+    pub fn append(&mut self, path: &Path, contents: &str) -> Result<(), String> {
+        match fs::metadata(path) {
+            Ok(metadata) => self.journal.push(Action::Appended {
+                path: path.to_path_buf(),
+                original_len: metadata.len(),
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                self.journal.push(Action::Created(path.to_path_buf()))
+            }
+            Err(err) => return Err(format!("Unable to read {path:?}: {err}")),
+        }
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(|err| format!("Unable to open {path:?}: {err}"))?;
+        file.write_all(contents.as_bytes())
+            .map_err(|err| format!("Unable to write to {path:?}: {err}"))
+    }
+
+    /// Copy the contents of a directory into another directory.
+    ///
+    /// Files which already exist at the destination with identical contents are left alone. If
+    /// any other file would be overwritten, nothing is copied and an error listing the
+    /// conflicting files is returned, unless `force` is set.
+    ///
+    /// * `from`: Directory containing some filesystem objects
+    /// * `to`: Directory where they should be copied
+    /// * `force`: Overwrite conflicting files
+    /// * `strategy`: How file contents should be transferred
+    pub fn copy_directory_contents(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        force: bool,
+        strategy: CopyStrategy,
+    ) -> Result<(), String> {
+        if !force {
+            let conflicts = find_conflicts(from, to)
+                .map_err(|err| format!("Unable to compare {from:?} with {to:?}: {err}"))?;
+            if !conflicts.is_empty() {
+                return Err(format!(
+                    "The following files already exist in {to:?} and would be overwritten:\n{}\n\
+                        Use --force to overwrite them.",
+                    conflicts
+                        .iter()
+                        .map(|path| format!("  {}", path.display()))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                ));
+            }
+        }
+        self.copy_tree(from, to, strategy)
+    }
+
+    fn copy_tree(&mut self, from: &Path, to: &Path, strategy: CopyStrategy) -> Result<(), String> {
+        let created = !to.exists();
+        self.create_dir_all(to)?;
+        let entries =
+            fs::read_dir(from).map_err(|err| format!("Unable to read {from:?}: {err}"))?;
+        for entry in entries {
+            let entry = entry.map_err(|err| format!("Unable to read {from:?}: {err}"))?;
+            let source = entry.path();
+            let destination = to.join(entry.file_name());
+            if is_real_dir(&source) {
+                self.copy_tree(&source, &destination, strategy)?;
+            } else if !is_identical(&source, &destination) {
+                self.copy_file(&source, &destination, strategy)?;
+            }
+        }
+        // Only directories araki created take on the metadata of the source; existing ones are
+        // left as the user had them
+        if created {
+            copy_metadata(from, to).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Keep all the changes made during the transaction.
+    pub fn commit(mut self) {
+        self.finished = true;
+        let _ = fs::remove_dir_all(&self.backup_dir);
+    }
+
+    /// Undo all the changes made during the transaction, most recent first.
+    ///
+    /// Every change is attempted even if some fail; the errors are returned together.
+    pub fn rollback(mut self) -> Result<(), String> {
+        self.undo()
+    }
+
+    fn undo(&mut self) -> Result<(), String> {
+        self.finished = true;
+        let mut errors = vec![];
+        while let Some(action) = self.journal.pop() {
+            debug!(action:? = action; "Undoing");
+            let result = match &action {
+                Action::Created(path) if path.is_dir() => fs::remove_dir_all(path),
+                Action::Created(path) => fs::remove_file(path),
+                Action::Overwritten { path, backup } => {
+                    copy_fs_obj(backup, path, CopyStrategy::Copy).map_err(|err| err.source)
+                }
+                Action::Appended { path, original_len } => OpenOptions::new()
+                    .write(true)
+                    .open(path)
+                    .and_then(|file| file.set_len(*original_len)),
+            };
+            match result {
+                Ok(_) => (),
+                // Already gone, e.g. a file inside a directory which was removed
+                Err(err) if err.kind() == ErrorKind::NotFound => (),
+                Err(err) => errors.push(format!("{action:?}: {err}")),
+            }
+        }
+        let _ = fs::remove_dir_all(&self.backup_dir);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unable to undo some changes:\n{}",
+                errors.join("\n")
+            ))
+        }
+    }
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.undo();
+        }
+    }
+}
+
+/// Check whether a path is a directory, without following symlinks.
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Check whether two files exist and have the same contents. Symlinks are identical if they
+/// point to the same target.
+fn is_identical(a: &Path, b: &Path) -> bool {
+    match (fs::read_link(a), fs::read_link(b)) {
+        (Ok(a), Ok(b)) => return a == b,
+        (Err(_), Err(_)) => (),
+        _ => return false,
+    }
+    match (fs::read(a), fs::read(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Find the files in `from` which exist in `to` with different contents.
+///
+/// * `from`: Directory to be copied
+/// * `to`: Destination directory
+fn find_conflicts(from: &Path, to: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut conflicts = vec![];
+    if !to.exists() {
+        return Ok(conflicts);
+    }
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let source = entry.path();
+        let destination = to.join(entry.file_name());
+        let exists = fs::symlink_metadata(&destination).is_ok();
+        if is_real_dir(&source) {
+            if is_real_dir(&destination) {
+                conflicts.extend(find_conflicts(&source, &destination)?);
+            } else if exists {
+                conflicts.push(destination);
+            }
+        } else if exists && !is_identical(&source, &destination) {
+            conflicts.push(destination);
+        }
+    }
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_restores_original_state() {
+        let root = temp_dir().join(format!("araki-test-{}", Uuid::new_v4()));
+        let source = root.join("source");
+        let target = root.join("target");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(source.join("README.md"), "remote").unwrap();
+        fs::write(source.join("nested").join("file"), "new").unwrap();
+        fs::write(target.join("README.md"), "local").unwrap();
+        fs::write(target.join(".gitignore"), "target/\n").unwrap();
+
+        let mut transaction = Transaction::new();
+        assert!(
+            transaction
+                .copy_directory_contents(&source, &target, false, CopyStrategy::Copy)
+                .is_err()
+        );
+        transaction
+            .copy_directory_contents(&source, &target, true, CopyStrategy::Copy)
+            .unwrap();
+        transaction
+            .append(&target.join(".gitignore"), ".araki-git/\n")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("README.md")).unwrap(),
+            "remote"
+        );
+        transaction.rollback().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("README.md")).unwrap(),
+            "local"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join(".gitignore")).unwrap(),
+            "target/\n"
+        );
+        assert!(!target.join("nested").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_copy_preserves_symlinks_and_permissions() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let root = temp_dir().join(format!("araki-test-{}", Uuid::new_v4()));
+        let source = root.join("source");
+        let target = root.join("target");
+        fs::create_dir_all(source.join("bin")).unwrap();
+        fs::write(source.join("bin").join("tool"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(
+            source.join("bin").join("tool"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        symlink("bin/tool", source.join("tool")).unwrap();
+        symlink("bin", source.join("scripts")).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction
+            .copy_directory_contents(&source, &target, false, CopyStrategy::Reflink)
+            .unwrap();
+        transaction.commit();
+
+        let mode = fs::metadata(target.join("bin").join("tool"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            fs::read_link(target.join("tool")).unwrap(),
+            PathBuf::from("bin/tool")
+        );
+        assert_eq!(
+            fs::read_link(target.join("scripts")).unwrap(),
+            PathBuf::from("bin")
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use jiff::civil::Date;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// How to compute the name of the next tag from the existing ones.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagBump {
+    /// Increment the major version of the latest semver tag, e.g. `v1.4.2` -> `v2.0.0`
+    Major,
+    /// Increment the minor version of the latest semver tag, e.g. `v1.4.2` -> `v1.5.0`
+    Minor,
+    /// Increment the patch version of the latest semver tag, e.g. `v1.4.2` -> `v1.4.3`
+    Patch,
+    /// Use the current year and month, with a counter for the tags of the month, e.g.
+    /// `2025.03.0`, `2025.03.1`
+    Calver,
+}
+
+impl FromStr for TagBump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(TagBump::Major),
+            "minor" => Ok(TagBump::Minor),
+            "patch" => Ok(TagBump::Patch),
+            "calver" => Ok(TagBump::Calver),
+            _ => Err(format!(
+                "'{s}' is not one of 'major', 'minor', 'patch' or 'calver'"
+            )),
+        }
+    }
+}
+
+impl Display for TagBump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TagBump::Major => "major",
+            TagBump::Minor => "minor",
+            TagBump::Patch => "patch",
+            TagBump::Calver => "calver",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A tag name of the form `[prefix]MAJOR.MINOR.PATCH`, e.g. `v1.4.2`, or a calver tag of the
+/// form `YYYY.MM.N`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version<'a> {
+    prefix: &'a str,
+    parts: [u64; 3],
+    calver: bool,
+}
+
+impl<'a> Version<'a> {
+    /// Parse a tag name, returning `None` if it isn't a version. The prefix may be empty or `v`.
+    fn parse(name: &'a str) -> Option<Self> {
+        let (prefix, version) = match name.strip_prefix('v') {
+            Some(version) => ("v", version),
+            None => ("", name),
+        };
+        let mut parts = [0; 3];
+        let mut widths = [0; 3];
+        let mut components = version.split('.');
+        for (part, width) in parts.iter_mut().zip(widths.iter_mut()) {
+            let component = components.next()?;
+            *width = component.len();
+            // Reject signs, which `parse` would accept
+            if component.is_empty() || !component.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            *part = component.parse().ok()?;
+        }
+        if components.next().is_some() {
+            return None;
+        }
+        let calver = prefix.is_empty() && widths[..2] == [4, 2] && (1..=12).contains(&parts[1]);
+        Some(Version {
+            prefix,
+            parts,
+            calver,
+        })
+    }
+}
+
+/// Compute the name of the next tag.
+///
+/// Semver bumps start from `v0.0.0` if there is no version tag yet, and keep the prefix of the
+/// latest version tag otherwise; calver tags are ignored. Calver tags are named `YYYY.MM.N`,
+/// where `N` counts the tags of the month from 0.
+///
+/// * `bump`: How to compute the next name
+/// * `existing`: Names of the existing tags
+/// * `today`: Current date, for calver tags
+pub fn next_tag_name<'a>(
+    bump: TagBump,
+    existing: impl IntoIterator<Item = &'a str>,
+    today: Date,
+) -> String {
+    let versions = existing.into_iter().filter_map(Version::parse);
+    if bump == TagBump::Calver {
+        let month = [today.year() as u64, today.month() as u64];
+        let next = versions
+            .filter(|version| version.calver && version.parts[..2] == month)
+            .map(|version| version.parts[2] + 1)
+            .max()
+            .unwrap_or(0);
+        return format!("{:04}.{:02}.{next}", month[0], month[1]);
+    }
+
+    let latest = versions
+        .filter(|version| !version.calver)
+        .max_by_key(|version| version.parts);
+    let (prefix, [major, minor, patch]) = match latest {
+        Some(version) => (version.prefix, version.parts),
+        None => ("v", [0, 0, 0]),
+    };
+    let [major, minor, patch] = match bump {
+        TagBump::Major => [major + 1, 0, 0],
+        TagBump::Minor => [major, minor + 1, 0],
+        TagBump::Patch | TagBump::Calver => [major, minor, patch + 1],
+    };
+    format!("{prefix}{major}.{minor}.{patch}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jiff::civil::date;
+
+    #[test]
+    fn test_next_tag_name() {
+        let today = date(2025, 3, 14);
+        let tags = [
+            "v1.4.2",
+            "v1.10.0",
+            "v1.9.9",
+            "latest-good",
+            "v1.2",
+            "v1.2.3.4",
+        ];
+        assert_eq!(next_tag_name(TagBump::Major, tags, today), "v2.0.0");
+        assert_eq!(next_tag_name(TagBump::Minor, tags, today), "v1.11.0");
+        assert_eq!(next_tag_name(TagBump::Patch, tags, today), "v1.10.1");
+        assert_eq!(next_tag_name(TagBump::Patch, ["1.0.0"], today), "1.0.1");
+        assert_eq!(next_tag_name(TagBump::Minor, [], today), "v0.1.0");
+
+        assert_eq!(next_tag_name(TagBump::Calver, tags, today), "2025.03.0");
+        let tags = ["2025.03.0", "2025.03.1", "2025.02.7", "v1.0.0"];
+        assert_eq!(next_tag_name(TagBump::Calver, tags, today), "2025.03.2");
+        assert_eq!(next_tag_name(TagBump::Major, tags, today), "v2.0.0");
+    }
+}
@@ -0,0 +1,289 @@
+use git2::{ErrorCode, Oid, Repository};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::common::{self, ARAKI_GIT_DIR_NAME, FileChange};
+use crate::diff::{self, PackageChange};
+use crate::error::ArakiError;
+use crate::lockspecs::{Dependency, LockSpec, Package};
+
+mod checkout;
+mod clone;
+mod compare;
+mod history;
+mod init;
+mod list;
+mod pull;
+mod push;
+mod revision;
+mod stash;
+mod tag;
+
+pub use checkout::CheckoutOptions;
+pub use clone::CloneOptions;
+pub use compare::LockSpecDiff;
+pub use history::HistoryEntry;
+pub use init::InitOptions;
+pub use list::{ListOptions, TagInfo, TagSort};
+pub use pull::PullOutcome;
+pub use push::PushOptions;
+pub use revision::{Revision, RevisionKind};
+pub use tag::TagOptions;
+
+/// Report progress through a multi-step operation. Called with the number of the step which is
+/// starting, the total number of steps and a description of the step.
+pub type Progress<'a> = &'a dyn Fn(usize, usize, &str);
+
+/// A directory containing a lockspec managed by araki, along with the `.araki-git` repository
+/// tracking its versions.
+pub struct Workspace {
+    repo: Repository,
+    lockspec: LockSpec,
+}
+
+impl Workspace {
+    /// Open the workspace in a directory.
+    ///
+    /// * `path`: Directory containing a lockspec and an `.araki-git` repository
+    pub fn open(path: impl AsRef<Path>) -> Result<Workspace, ArakiError> {
+        let path = path.as_ref();
+        let araki_git_dir = path.join(ARAKI_GIT_DIR_NAME);
+        if !araki_git_dir.exists() {
+            return Err(ArakiError::NotManaged(path.to_path_buf()));
+        }
+        let repo = Repository::open(araki_git_dir)
+            .map_err(ArakiError::git("Couldn't recognize the araki repo"))?;
+        let workdir = common::get_araki_workdir(&repo);
+        let lockspec =
+            LockSpec::from_path(&workdir).map_err(|_| ArakiError::NoLockSpec(workdir))?;
+        Ok(Workspace { repo, lockspec })
+    }
+
+    /// Open the workspace containing a directory, looking for `.araki-git` in the directory
+    /// and then in each of its parents, the way git looks for `.git`.
+    ///
+    /// * `start`: Directory to start looking from
+    pub fn discover(start: impl AsRef<Path>) -> Result<Workspace, ArakiError> {
+        let start = start.as_ref();
+        let root = find_root(start).ok_or_else(|| ArakiError::NotManaged(start.to_path_buf()))?;
+        Workspace::open(root)
+    }
+
+    /// Directory containing the lockspec.
+    pub fn path(&self) -> &Path {
+        &self.lockspec.path
+    }
+
+    /// The `.araki-git` repository tracking the lockspec.
+    pub fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    pub fn lockspec(&self) -> &LockSpec {
+        &self.lockspec
+    }
+
+    /// List the files tracked for the lockspec which changed since the last tag.
+    pub fn status(&self) -> Result<Vec<FileChange>, ArakiError> {
+        common::lockspec_status(&self.repo, &self.lockspec).map_err(ArakiError::LockSpec)
+    }
+
+    /// Check that the lockfile is consistent with the specfile.
+    pub fn check(&self) -> Result<(), ArakiError> {
+        check_lockspec(&self.lockspec)
+    }
+
+    /// Map each tagged commit to the name of its tag. If a commit has several tags, the one
+    /// created last wins.
+    fn tags_by_commit(&self) -> Result<HashMap<Oid, String>, ArakiError> {
+        let names = self
+            .repo
+            .tag_names(None)
+            .map_err(ArakiError::git("Unable to list tags"))?;
+        let mut tags_by_commit: HashMap<Oid, (i64, &str)> = HashMap::new();
+        for name in names.iter().flatten().flatten() {
+            let Ok(obj) = self.repo.revparse_single(&format!("refs/tags/{name}")) else {
+                continue;
+            };
+            let time = obj
+                .as_tag()
+                .and_then(|tag| tag.tagger())
+                .map_or(0, |tagger| tagger.when().seconds());
+            if let Ok(commit) = obj.peel_to_commit() {
+                let entry = tags_by_commit.entry(commit.id()).or_insert((time, name));
+                if time >= entry.0 {
+                    *entry = (time, name);
+                }
+            }
+        }
+        Ok(tags_by_commit
+            .into_iter()
+            .map(|(commit, (_, name))| (commit, name.to_string()))
+            .collect())
+    }
+
+    /// Find the most recent tag reachable from HEAD, if any.
+    pub fn last_tag(&self) -> Result<Option<String>, ArakiError> {
+        let tags_by_commit = self.tags_by_commit()?;
+
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(ArakiError::git("Unable to walk the history"))?;
+        match revwalk.push_head() {
+            Ok(()) => {}
+            // Nothing has been committed yet
+            Err(err) if err.code() == ErrorCode::UnbornBranch => return Ok(None),
+            Err(err) => return Err(ArakiError::git("Unable to find HEAD")(err)),
+        }
+        for oid in revwalk {
+            let oid = oid.map_err(ArakiError::git("Unable to walk the history"))?;
+            if let Some(name) = tags_by_commit.get(&oid) {
+                return Ok(Some(name.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the packages pinned in the lockfile at a revision. A revision without the
+    /// lockfile has no packages.
+    ///
+    /// * `revision`: Revision to read the lockfile at, e.g. `HEAD` or `refs/tags/v1`
+    pub fn packages_at(&self, revision: &str) -> Result<Vec<Package>, ArakiError> {
+        let lockfile_name = self.lockspec.format.lockfile_name();
+        match common::read_file_at_revision(&self.repo, revision, lockfile_name) {
+            Ok(contents) => self
+                .lockspec
+                .format
+                .parse_packages(&contents)
+                .map_err(|err| {
+                    ArakiError::LockSpec(format!(
+                        "Unable to parse {lockfile_name} at {revision}: {err}"
+                    ))
+                }),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(vec![]),
+            Err(err) => Err(ArakiError::git(format!(
+                "Unable to read {lockfile_name} at {revision}"
+            ))(err)),
+        }
+    }
+
+    /// Parse the dependencies declared in the specfile at a revision. A revision without the
+    /// specfile has no dependencies.
+    ///
+    /// * `revision`: Revision to read the specfile at, e.g. `HEAD` or `refs/tags/v1`
+    pub fn dependencies_at(&self, revision: &str) -> Result<Vec<Dependency>, ArakiError> {
+        let specfile_name = self.lockspec.format.specfile_name();
+        match common::read_file_at_revision(&self.repo, revision, specfile_name) {
+            Ok(contents) => self
+                .lockspec
+                .format
+                .parse_dependencies(&contents)
+                .map_err(|err| {
+                    ArakiError::LockSpec(format!(
+                        "Unable to parse {specfile_name} at {revision}: {err}"
+                    ))
+                }),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(vec![]),
+            Err(err) => Err(ArakiError::git(format!(
+                "Unable to read {specfile_name} at {revision}"
+            ))(err)),
+        }
+    }
+
+    /// Compare the packages pinned in the lockfile with a revision.
+    ///
+    /// * `revision`: Revision to compare with; every package is new if there is none
+    pub fn package_changes(
+        &self,
+        revision: Option<&str>,
+    ) -> Result<Vec<PackageChange>, ArakiError> {
+        let old = match revision {
+            Some(revision) => self.packages_at(revision)?,
+            None => vec![],
+        };
+        let new = self.lockspec.packages().map_err(ArakiError::LockSpec)?;
+        Ok(diff::diff_packages(&old, &new))
+    }
+}
+
+/// Find the directory of the workspace containing a directory: the closest of the directory
+/// and its parents which has an `.araki-git` repository.
+///
+/// * `start`: Directory to start looking from
+pub fn find_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(ARAKI_GIT_DIR_NAME).is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Check that the lockfile of a lockspec is consistent with its specfile, returning
+/// `ArakiError::Inconsistent` with every problem found if it isn't.
+///
+/// * `lockspec`: Lockspec to check
+pub fn check_lockspec(lockspec: &LockSpec) -> Result<(), ArakiError> {
+    let problems = lockspec
+        .check()
+        .map_err(|err| ArakiError::LockSpec(format!("Unable to check the lockspec: {err}")))?;
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ArakiError::Inconsistent(problems))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::env::temp_dir;
+    use std::fs;
+    use uuid::Uuid;
+
+    /// Create a workspace with a pixi lockspec in a temporary directory, with nothing
+    /// committed yet on the `main` branch.
+    pub fn workspace() -> Workspace {
+        let dir = temp_dir().join(format!("araki-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pixi.toml"), "[workspace]\nname = \"test\"\n").unwrap();
+        fs::write(dir.join("pixi.lock"), "version: 6\n").unwrap();
+
+        let repo = Repository::init(dir.join(ARAKI_GIT_DIR_NAME)).unwrap();
+        repo.set_workdir(&dir, true).unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        Workspace::open(&dir).unwrap()
+    }
+
+    /// Commit the current lockspec files to HEAD, returning the new commit.
+    ///
+    /// * `workspace`: Workspace to commit in
+    /// * `message`: Commit message
+    pub fn commit(workspace: &Workspace, message: &str) -> Oid {
+        let repo = &workspace.repo;
+        let mut index = common::stage_lockspec(repo, &workspace.lockspec).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("araki", "araki@example.com").unwrap();
+        let parent = repo.head().and_then(|head| head.peel_to_commit()).ok();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            parent.as_slice().iter().collect::<Vec<_>>().as_slice(),
+        )
+        .unwrap()
+    }
+
+    /// Append a line to a file of the workspace.
+    ///
+    /// * `workspace`: Workspace containing the file
+    /// * `file`: Path of the file, relative to the workspace
+    /// * `line`: Line to append
+    pub fn append(workspace: &Workspace, file: &str, line: &str) {
+        let path = workspace.path().join(file);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(path, format!("{contents}{line}\n")).unwrap();
+    }
+}
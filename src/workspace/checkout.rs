@@ -0,0 +1,313 @@
+use git2::Oid;
+use git2::build::CheckoutBuilder;
+use log::{info, warn};
+
+use super::{Revision, RevisionKind, Workspace};
+use crate::config::UserConfig;
+use crate::error::ArakiError;
+use crate::lockspecs::LockSpec;
+use crate::signing;
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckoutOptions {
+    /// Discard uncommitted changes to the lockspec
+    pub force: bool,
+    /// Save uncommitted changes to the lockspec with `stash` before checking out
+    pub stash: bool,
+    /// Only check out the lockspec files, without installing the environment
+    pub no_install: bool,
+    /// Only install this environment of the lockspec, e.g. `default` for pixi
+    pub environment: Option<String>,
+}
+
+/// Where HEAD pointed before a checkout, so that a failed checkout can be undone.
+struct PreviousHead {
+    /// Branch HEAD was attached to, if any
+    branch: Option<String>,
+    commit: Oid,
+    /// Commit saving the uncommitted changes to the lockspec, if there were any
+    snapshot: Option<Oid>,
+    /// Whether the snapshot is the stash
+    stashed: bool,
+}
+
+impl Workspace {
+    /// Check out a revision of the lockspec and install it, returning what the revision was
+    /// resolved to.
+    ///
+    /// Checking out a branch keeps HEAD attached to it, so that `tag` commits onto the branch.
+    /// Any other revision detaches HEAD. The signature of a tag is verified if the user trusts
+    /// any signer, and tags and commits are refused if the user requires trusted signatures
+    /// and they have none.
+    ///
+    /// Uncommitted changes to the lockspec are never overwritten, unless `options` says to
+    /// discard or stash them. If the install fails, the previous HEAD and lockspec files,
+    /// including any uncommitted changes, are restored, and the previous lockspec is installed
+    /// again so that the environment matches them.
+    ///
+    /// * `revision`: Tag, branch or other revision; see `resolve` for the syntax
+    /// * `options`: Options controlling what happens to uncommitted changes and the install
+    pub fn checkout(
+        &mut self,
+        revision: &str,
+        options: &CheckoutOptions,
+    ) -> Result<Revision, ArakiError> {
+        let resolved = self.resolve(revision)?;
+        match &resolved.kind {
+            RevisionKind::Tag(name) => {
+                signing::check_tag(&self.repo, name, &UserConfig::load()?.signing)?
+            }
+            RevisionKind::Commit => {
+                signing::check_commit(&self.repo, resolved.commit, &UserConfig::load()?.signing)?
+            }
+            RevisionKind::Branch(_) => {}
+        }
+
+        let mut previous = self.previous_head()?;
+        let changes = self.status()?;
+        if !changes.is_empty() {
+            if options.stash {
+                previous.snapshot = Some(self.stash()?);
+                previous.stashed = true;
+            } else if options.force {
+                previous.snapshot = Some(self.snapshot()?);
+            } else {
+                return Err(ArakiError::DirtyWorkspace(changes));
+            }
+        }
+
+        if let Err(error) = self.switch_and_install(revision, &resolved, options) {
+            let restored = self.restore(&previous).and_then(|()| {
+                // The failed install may have left the environment half updated
+                if options.no_install {
+                    return Ok(());
+                }
+                self.lockspec
+                    .install(options.environment.as_deref())
+                    .map_err(|err| {
+                        ArakiError::LockSpec(format!(
+                            "Failed to reinstall the previous checkout with {}, so the environment \
+                                doesn't match the lockspec until it is installed again: {err}",
+                            self.lockspec.format.name()
+                        ))
+                    })
+            });
+            return Err(match restored {
+                Ok(()) => {
+                    warn!(commit:% = previous.commit; "Restored the previous checkout");
+                    error
+                }
+                Err(rollback) => ArakiError::Rollback {
+                    error: Box::new(error),
+                    rollback: rollback.to_string(),
+                },
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// Record where HEAD points before a checkout.
+    fn previous_head(&self) -> Result<PreviousHead, ArakiError> {
+        let head = self
+            .repo
+            .head()
+            .map_err(ArakiError::git("Unable to read HEAD"))?;
+        let branch = if head.is_branch() {
+            Some(
+                head.name()
+                    .map_err(ArakiError::git(
+                        "Could not convert branch reference into name.",
+                    ))?
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+        let commit = head
+            .peel_to_commit()
+            .map_err(ArakiError::git("Unable to find the HEAD commit"))?;
+        Ok(PreviousHead {
+            branch,
+            commit: commit.id(),
+            snapshot: None,
+            stashed: false,
+        })
+    }
+
+    /// Check out the files of a resolved revision, point HEAD to it and install it.
+    ///
+    /// * `revision`: Revision as given by the user
+    /// * `resolved`: What the revision was resolved to
+    /// * `options`: Options passed to `checkout`
+    fn switch_and_install(
+        &mut self,
+        revision: &str,
+        resolved: &Revision,
+        options: &CheckoutOptions,
+    ) -> Result<(), ArakiError> {
+        let branch = match &resolved.kind {
+            RevisionKind::Branch(name) => Some(format!("refs/heads/{name}")),
+            _ => None,
+        };
+        self.switch(resolved.commit, branch.as_deref())?;
+        info!(revision = revision, resolved:% = resolved; "Checked out");
+
+        if options.no_install {
+            return Ok(());
+        }
+        self.lockspec
+            .install(options.environment.as_deref())
+            .map_err(|err| {
+                ArakiError::LockSpec(format!(
+                    "Failed to install {revision} with {}: {err}",
+                    self.lockspec.format.name()
+                ))
+            })
+    }
+
+    /// Check out the files of a commit, discarding any changes, and point HEAD to it.
+    ///
+    /// * `commit`: Commit to check out
+    /// * `branch`: Branch to attach HEAD to, e.g. `refs/heads/main`; HEAD is detached if unset
+    fn switch(&mut self, commit: Oid, branch: Option<&str>) -> Result<(), ArakiError> {
+        let commit = self
+            .repo
+            .find_commit(commit)
+            .map_err(ArakiError::git(format!("Unable to find commit {commit}")))?;
+        self.repo
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+            .map_err(ArakiError::git(format!(
+                "Unable to check out commit {}",
+                commit.id()
+            )))?;
+        match branch {
+            Some(branch) => self.repo.set_head(branch),
+            None => self.repo.set_head_detached(commit.id()),
+        }
+        .map_err(ArakiError::git("Unable to set head"))?;
+
+        // The checked out files may belong to a different lockspec format
+        self.lockspec = LockSpec::from_path(self.path())
+            .map_err(|_| ArakiError::NoLockSpec(self.path().to_path_buf()))?;
+        Ok(())
+    }
+
+    /// Undo a checkout: point HEAD back to where it was and restore the lockspec files.
+    ///
+    /// * `previous`: Where HEAD pointed before the checkout
+    fn restore(&mut self, previous: &PreviousHead) -> Result<(), ArakiError> {
+        self.switch(previous.commit, previous.branch.as_deref())?;
+        if let Some(snapshot) = previous.snapshot {
+            self.restore_snapshot(snapshot)?;
+            if previous.stashed {
+                self.drop_stash()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::tests::{append, commit, workspace};
+    use std::fs;
+
+    const NO_INSTALL: CheckoutOptions = CheckoutOptions {
+        force: false,
+        stash: false,
+        no_install: true,
+        environment: None,
+    };
+
+    /// Create a workspace with a tag `v1`, a later commit on `main` and an uncommitted change.
+    fn dirty_workspace() -> (Workspace, Oid, Oid) {
+        let workspace = workspace();
+        let v1 = commit(&workspace, "v1");
+        let target = workspace.repo.find_object(v1, None).unwrap();
+        workspace
+            .repo
+            .tag_lightweight("v1", &target, false)
+            .unwrap();
+        drop(target);
+        append(&workspace, "pixi.toml", "# v2");
+        let v2 = commit(&workspace, "v2");
+        append(&workspace, "pixi.lock", "# uncommitted");
+        (workspace, v1, v2)
+    }
+
+    fn read(workspace: &Workspace, file: &str) -> String {
+        fs::read_to_string(workspace.path().join(file)).unwrap()
+    }
+
+    fn head(workspace: &Workspace) -> (Option<String>, Oid) {
+        let head = workspace.repo.head().unwrap();
+        let branch = head.is_branch().then(|| head.name().unwrap().to_string());
+        (branch, head.peel_to_commit().unwrap().id())
+    }
+
+    #[test]
+    fn test_checkout_refuses_dirty_workspace() {
+        let (mut workspace, _, v2) = dirty_workspace();
+        let result = workspace.checkout("v1", &NO_INSTALL);
+        assert!(matches!(result, Err(ArakiError::DirtyWorkspace(changes)) if changes.len() == 1));
+        assert_eq!(head(&workspace), (Some("refs/heads/main".to_string()), v2));
+        assert!(read(&workspace, "pixi.lock").contains("# uncommitted"));
+
+        let force = CheckoutOptions {
+            force: true,
+            ..NO_INSTALL
+        };
+        workspace.checkout("v1", &force).unwrap();
+        assert!(!read(&workspace, "pixi.lock").contains("# uncommitted"));
+        fs::remove_dir_all(workspace.path()).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_stash() {
+        let (mut workspace, v1, v2) = dirty_workspace();
+        let stash = CheckoutOptions {
+            stash: true,
+            ..NO_INSTALL
+        };
+        let resolved = workspace.checkout("v1", &stash).unwrap();
+        assert_eq!(resolved.kind, RevisionKind::Tag("v1".to_string()));
+        assert_eq!(head(&workspace), (None, v1));
+        assert!(workspace.status().unwrap().is_empty());
+        assert!(matches!(workspace.stash(), Err(ArakiError::StashExists)));
+
+        // The stash is restored on top of the branch it was saved from
+        workspace.checkout("latest", &NO_INSTALL).unwrap();
+        assert_eq!(head(&workspace), (Some("refs/heads/main".to_string()), v2));
+        workspace.stash_pop().unwrap();
+        assert!(read(&workspace, "pixi.lock").contains("# uncommitted"));
+        assert!(matches!(workspace.stash_pop(), Err(ArakiError::NoStash)));
+        fs::remove_dir_all(workspace.path()).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_rolls_back() {
+        let mut workspace = workspace();
+        commit(&workspace, "with a lockfile");
+        fs::remove_file(workspace.path().join("pixi.lock")).unwrap();
+        let broken = commit(&workspace, "without a lockfile");
+        fs::write(workspace.path().join("pixi.lock"), "version: 6\n").unwrap();
+        let fixed = commit(&workspace, "with the lockfile again");
+        append(&workspace, "pixi.toml", "# uncommitted");
+
+        // Checking out a commit without a lockspec fails after switching to it
+        let force = CheckoutOptions {
+            force: true,
+            ..NO_INSTALL
+        };
+        let result = workspace.checkout(&broken.to_string(), &force);
+        assert!(matches!(result, Err(ArakiError::NoLockSpec(_))));
+        assert_eq!(
+            head(&workspace),
+            (Some("refs/heads/main".to_string()), fixed)
+        );
+        assert!(read(&workspace, "pixi.toml").contains("# uncommitted"));
+        assert!(workspace.path().join("pixi.lock").exists());
+        fs::remove_dir_all(workspace.path()).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use git2::Repository;
+use log::warn;
+use std::path::Path;
+
+use super::Workspace;
+use crate::common::{self, ARAKI_GIT_DIR_NAME};
+use crate::config::UserConfig;
+use crate::error::ArakiError;
+use crate::lockspecs::LockSpec;
+use crate::registry;
+use crate::remote::RemoteRepo;
+use crate::signing;
+use crate::transaction::Transaction;
+
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Overwrite files in the target directory which conflict with the lockspec repository
+    pub force: bool,
+}
+
+impl Workspace {
+    /// Clone a lockspec repository into a directory and install the lockspec.
+    ///
+    /// If anything fails, the directory is restored to its original state.
+    ///
+    /// * `remote`: Lockspec repository
+    /// * `path`: Directory where the lockspec should be cloned
+    /// * `options`: Options controlling how the lockspec is cloned
+    pub fn clone(
+        remote: &RemoteRepo,
+        path: &Path,
+        options: &CloneOptions,
+    ) -> Result<Workspace, ArakiError> {
+        // Check that the target directory has no existing lockspec
+        if !options.force && LockSpec::from_path(path).is_ok() {
+            return Err(ArakiError::LockSpecExists(path.to_path_buf()));
+        }
+
+        // Keep track of every change made to the target directory, so that it can be restored
+        // to its original state if cloning or installing fails
+        let mut transaction = Transaction::new();
+        if let Err(error) = clone_and_install(remote, path, options.force, &mut transaction) {
+            return Err(match transaction.rollback() {
+                Ok(()) => error,
+                Err(rollback) => ArakiError::Rollback {
+                    error: Box::new(error),
+                    rollback,
+                },
+            });
+        }
+        transaction.commit();
+
+        // The registry is only used to list environments, so failing to update it shouldn't
+        // fail the clone
+        if let Err(err) = registry::register(&remote.get_repo(), path, Some(remote.as_url())) {
+            warn!("Unable to record the environment in the registry: {err}");
+        }
+        Workspace::open(path)
+    }
+}
+
+/// Clone a lockspec repository into a directory and install the lockspec.
+///
+/// * `remote`: Lockspec repository
+/// * `path`: Directory where the lockspec should be cloned
+/// * `force`: Overwrite files in `path` which conflict with the lockspec repository
+/// * `transaction`: Transaction recording the changes made to `path`
+fn clone_and_install(
+    remote: &RemoteRepo,
+    path: &Path,
+    force: bool,
+    transaction: &mut Transaction,
+) -> Result<(), ArakiError> {
+    common::git_clone(remote.as_ssh_url(), path, transaction, force)
+        .map_err(|err| ArakiError::Other(format!("Unable to clone the lockspec: {err}")))?;
+
+    // Refuse to install an environment without provenance if the user requires it
+    let signing = UserConfig::load()?.signing;
+    if signing.require_signed_tags {
+        let repo = Repository::open(path.join(ARAKI_GIT_DIR_NAME))
+            .map_err(ArakiError::git("Couldn't open the cloned araki repo"))?;
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(ArakiError::git("Unable to find the cloned commit"))?;
+        signing::check_commit(&repo, head.id(), &signing)?;
+    }
+
+    let lockspec = LockSpec::from_path(path).map_err(|_| {
+        ArakiError::LockSpec(format!(
+            "The cloned lockspec repo is not valid. Is the specfile or lockfile missing from \
+                {}/{} ?",
+            remote.get_org(),
+            remote.get_repo()
+        ))
+    })?;
+
+    lockspec.install(None).map_err(|err| {
+        ArakiError::LockSpec(format!(
+            "Failed to install the environment with {}: {err}",
+            lockspec.format.name()
+        ))
+    })
+}
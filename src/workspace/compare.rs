@@ -0,0 +1,54 @@
+use super::{Revision, Workspace};
+use crate::diff::{self, DependencyChange, PackageChange};
+use crate::error::ArakiError;
+
+/// The differences between two versions of a lockspec.
+#[derive(Debug, Clone)]
+pub struct LockSpecDiff {
+    /// Revision compared from
+    pub from: Revision,
+    /// Revision compared to, or `None` for the files in the workspace
+    pub to: Option<Revision>,
+    /// Dependencies whose declaration in the specfile changed
+    pub dependencies: Vec<DependencyChange>,
+    /// Packages whose version pinned in the lockfile changed
+    pub packages: Vec<PackageChange>,
+}
+
+impl Workspace {
+    /// Compare the dependencies and packages of two revisions of the lockspec.
+    ///
+    /// * `from`: Revision to compare from; defaults to `HEAD`
+    /// * `to`: Revision to compare to; defaults to the files in the workspace, including changes
+    ///   which haven't been tagged
+    pub fn diff(&self, from: Option<&str>, to: Option<&str>) -> Result<LockSpecDiff, ArakiError> {
+        let from = self.resolve(from.unwrap_or("HEAD"))?;
+        let to = to.map(|to| self.resolve(to)).transpose()?;
+
+        let from_commit = from.commit.to_string();
+        let (old_dependencies, old_packages) = (
+            self.dependencies_at(&from_commit)?,
+            self.packages_at(&from_commit)?,
+        );
+        let (new_dependencies, new_packages) = match &to {
+            Some(to) => {
+                let to_commit = to.commit.to_string();
+                (
+                    self.dependencies_at(&to_commit)?,
+                    self.packages_at(&to_commit)?,
+                )
+            }
+            None => (
+                self.lockspec.dependencies().map_err(ArakiError::LockSpec)?,
+                self.lockspec.packages().map_err(ArakiError::LockSpec)?,
+            ),
+        };
+
+        Ok(LockSpecDiff {
+            from,
+            to,
+            dependencies: diff::diff_dependencies(&old_dependencies, &new_dependencies),
+            packages: diff::diff_packages(&old_packages, &new_packages),
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use git2::{BranchType, Oid, Sort};
+use jiff::Timestamp;
+use std::collections::HashMap;
+
+use super::Workspace;
+use super::revision::commit_time;
+use crate::error::ArakiError;
+
+/// A commit of the lockspec history, along with the branches and tags pointing to it.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub commit: Oid,
+    /// Parents of the commit, first parent first
+    pub parents: Vec<Oid>,
+    /// Time the commit was made
+    pub time: Timestamp,
+    /// First line of the message of the commit's tag, or of the commit if it has no annotated
+    /// tag
+    pub summary: String,
+    pub branches: Vec<String>,
+    pub tags: Vec<String>,
+    /// Whether HEAD points to the commit
+    pub head: bool,
+}
+
+impl Workspace {
+    /// List the commits reachable from HEAD, the branches and the tags, children before their
+    /// parents and otherwise newest first, the way `git log --graph` orders them.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, ArakiError> {
+        let walk_error = || ArakiError::git("Unable to walk the history");
+        let mut revwalk = self.repo.revwalk().map_err(walk_error())?;
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(walk_error())?;
+
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .ok()
+            .map(|commit| commit.id());
+        if let Some(head) = head {
+            revwalk.push(head).map_err(walk_error())?;
+        }
+
+        let mut branches: HashMap<Oid, Vec<String>> = HashMap::new();
+        let local_branches = self
+            .repo
+            .branches(Some(BranchType::Local))
+            .map_err(ArakiError::git("Unable to list branches"))?;
+        for branch in local_branches {
+            let (branch, _) = branch.map_err(ArakiError::git("Unable to list branches"))?;
+            let (Ok(Some(name)), Some(commit)) = (branch.name(), branch.get().target()) else {
+                continue;
+            };
+            branches.entry(commit).or_default().push(name.to_string());
+            revwalk.push(commit).map_err(walk_error())?;
+        }
+
+        let mut tags: HashMap<Oid, Vec<(String, Option<String>)>> = HashMap::new();
+        let names = self
+            .repo
+            .tag_names(None)
+            .map_err(ArakiError::git("Unable to list tags"))?;
+        for name in names.iter().flatten().flatten() {
+            let Ok(obj) = self.repo.revparse_single(&format!("refs/tags/{name}")) else {
+                continue;
+            };
+            let message = obj
+                .as_tag()
+                .and_then(|tag| tag.message().ok().flatten())
+                .map(str::to_string);
+            if let Ok(commit) = obj.peel_to_commit() {
+                tags.entry(commit.id())
+                    .or_default()
+                    .push((name.to_string(), message));
+                revwalk.push(commit.id()).map_err(walk_error())?;
+            }
+        }
+
+        let mut history = vec![];
+        for oid in revwalk {
+            let commit = oid
+                .and_then(|oid| self.repo.find_commit(oid))
+                .map_err(walk_error())?;
+            let mut commit_tags = tags.remove(&commit.id()).unwrap_or_default();
+            commit_tags.sort();
+            let message = commit_tags
+                .iter()
+                .find_map(|(_, message)| message.as_deref())
+                .or(commit.message().ok())
+                .unwrap_or_default();
+            history.push(HistoryEntry {
+                commit: commit.id(),
+                parents: commit.parent_ids().collect(),
+                time: commit_time(&commit),
+                summary: message
+                    .lines()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or_default()
+                    .to_string(),
+                branches: branches.remove(&commit.id()).unwrap_or_default(),
+                tags: commit_tags.into_iter().map(|(name, _)| name).collect(),
+                head: head == Some(commit.id()),
+            });
+        }
+        Ok(history)
+    }
+}
@@ -0,0 +1,217 @@
+use git2::Repository;
+use log::warn;
+use std::path::Path;
+
+use super::{Progress, Workspace};
+use crate::backends::{self, Backend};
+use crate::common::{self, ARAKI_GIT_DIR_NAME};
+use crate::error::ArakiError;
+use crate::identity::{self, Identity};
+use crate::lockspecs::LockSpec;
+use crate::registry;
+use crate::transaction::Transaction;
+
+const ORG: &str = "nos-environments";
+
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    /// Message of the initial commit
+    pub message: Option<String>,
+    /// Overwrite files in the target directory which conflict with the lockspec repository
+    pub force: bool,
+    /// Author of the initial commit; resolved with `identity::resolve_author` if unset
+    pub author: Option<Identity>,
+    /// Committer of the initial commit; defaults to the author
+    pub committer: Option<Identity>,
+}
+
+impl Workspace {
+    /// Create a new lockspec repository on the backend from the lockspec in a directory, and
+    /// start managing the directory with araki.
+    ///
+    /// If anything fails after the repository is created, the directory is restored to its
+    /// original state.
+    ///
+    /// * `path`: Directory containing the lockspec
+    /// * `name`: Name of the lockspec repository
+    /// * `options`: Options controlling how the workspace is created
+    /// * `progress`: Called at the start of each step
+    pub async fn init(
+        path: &Path,
+        name: &str,
+        options: &InitOptions,
+        progress: Progress<'_>,
+    ) -> Result<Workspace, ArakiError> {
+        if path.join(ARAKI_GIT_DIR_NAME).exists() {
+            return Err(ArakiError::AlreadyManaged(path.to_path_buf()));
+        }
+
+        // Ensure the project has a lockspec, e.g. a pixi.toml and pixi.lock
+        let lockspec =
+            LockSpec::from_path(path).map_err(|_| ArakiError::NoLockSpec(path.to_path_buf()))?;
+
+        // Resolve the identity up front, so that nothing is created if it can't be determined
+        let config = lockspec.config().map_err(ArakiError::LockSpec)?;
+        let author = identity::resolve_author(None, options.author.as_ref(), &config).await?;
+        let committer = identity::resolve_committer(options.committer.as_ref(), &config, &author)?;
+
+        // Create a new respository
+        let backend = backends::get_current_backend()
+            .map_err(ArakiError::backend("Unable to get the current backend"))?;
+        progress(
+            1,
+            4,
+            &format!(
+                "Creating lockspec repository at {}...",
+                backend.get_repo_info(ORG, name).as_url()
+            ),
+        );
+        backend
+            .create_repository(ORG, name)
+            .await
+            .map_err(ArakiError::backend(format!(
+                "Error creating a new repository '{name}' for organization '{ORG}'"
+            )))?;
+
+        // Everything from here on modifies the target directory, so keep track of the changes
+        // in order to restore it if anything fails
+        let mut transaction = Transaction::new();
+        let result = commit_and_push(
+            path,
+            name,
+            options,
+            (&author, &committer),
+            &backend,
+            &mut transaction,
+            progress,
+        );
+        match result {
+            Ok(workspace) => {
+                transaction.commit();
+                // The registry is only used to list environments, so failing to update it
+                // shouldn't fail the initialization
+                let remote = backend.get_repo_info(ORG, name).as_url();
+                if let Err(err) = registry::register(name, path, Some(remote)) {
+                    warn!("Unable to record the environment in the registry: {err}");
+                }
+                Ok(workspace)
+            }
+            Err(error) => {
+                // Don't leave the new repository behind, otherwise retrying would fail because it
+                // already exists
+                let url = backend.get_repo_info(ORG, name).as_url();
+                let error = match backend.delete_repository(ORG, name).await {
+                    Ok(()) => error,
+                    Err(err) => ArakiError::Other(format!(
+                        "{error}\nUnable to delete the lockspec repository at {url}: {err}\n\
+                         Delete it before running `araki init` again."
+                    )),
+                };
+                Err(match transaction.rollback() {
+                    Ok(()) => error,
+                    Err(rollback) => ArakiError::Rollback {
+                        error: Box::new(error),
+                        rollback,
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// Clone the new lockspec repository into the target directory, commit the lockspec and push it.
+///
+/// * `path`: Directory containing the lockspec
+/// * `name`: Name of the lockspec repository
+/// * `options`: Options passed to `init`
+/// * `(author, committer)`: Identities the initial commit is attributed to
+/// * `backend`: Backend hosting the lockspec repository
+/// * `transaction`: Transaction recording the changes made to the target directory
+/// * `progress`: Called at the start of each step
+fn commit_and_push(
+    path: &Path,
+    name: &str,
+    options: &InitOptions,
+    (author, committer): (&Identity, &Identity),
+    backend: &impl Backend,
+    transaction: &mut Transaction,
+    progress: Progress<'_>,
+) -> Result<Workspace, ArakiError> {
+    // Clone the repository to the target directory. This also creates a .araki-git for tracking
+    // lockspec git versions
+    progress(2, 4, &format!("Cloning lockspec repository to {path:?}..."));
+    common::git_clone(
+        backend.get_repo_info(ORG, name).as_ssh_url(),
+        path,
+        transaction,
+        options.force,
+    )
+    .map_err(|err| ArakiError::Other(format!("Failed to clone the repository: {err}")))?;
+
+    // Commit the lockspec as a new change
+    progress(3, 4, "Committing lockspec...");
+    let workspace = Workspace::open(path)?;
+    commit_initial(
+        workspace.repo(),
+        workspace.lockspec(),
+        options.message.as_deref(),
+        (author, committer),
+    )?;
+
+    // Push to remote
+    progress(4, 4, "Pushing changes to remote...");
+    common::git_push(workspace.repo(), "origin", &["refs/heads/main"])
+        .map_err(ArakiError::git("Unable to push to remote"))?;
+    Ok(workspace)
+}
+
+// Committing is complicated with libgit2. See
+// https://users.rust-lang.org/t/how-can-i-do-git-add-some-file-rs-git-commit-m-message-git-push-with-git2-crate-on-a-bare-repo/94109/4
+// for the approach used here.
+/// Commit the lockspec as the first commit of the `main` branch, and check it out.
+///
+/// * `repo`: Freshly cloned araki git repository
+/// * `lockspec`: Lockspec to commit
+/// * `message`: Commit message
+/// * `(author, committer)`: Identities the commit is attributed to
+fn commit_initial(
+    repo: &Repository,
+    lockspec: &LockSpec,
+    message: Option<&str>,
+    (author, committer): (&Identity, &Identity),
+) -> Result<(), ArakiError> {
+    let mut index = common::stage_lockspec(repo, lockspec)?;
+    let new_tree_oid = index.write_tree().map_err(ArakiError::git(
+        "Failed to write the git tree from the index",
+    ))?;
+    let new_tree = repo.find_tree(new_tree_oid).map_err(ArakiError::git(
+        "Unable to find the git tree associated with the new commit",
+    ))?;
+    let commit_oid = repo
+        .commit(
+            None,
+            &author.signature()?,
+            &committer.signature()?,
+            message.unwrap_or("Initial commit"),
+            &new_tree,
+            &[],
+        )
+        .map_err(ArakiError::git("Error committing changes"))?;
+
+    // Create a new (default) branch called 'main'
+    let commit = repo
+        .find_commit(commit_oid)
+        .map_err(ArakiError::git("Unable to find the new commit"))?;
+    let branch = repo.branch("main", &commit, true).map_err(ArakiError::git(
+        "Unable to generate a main branch with the new commit",
+    ))?;
+
+    // Set the head to the new branch reference
+    let branch_ref = branch.into_reference();
+    let branch_ref_name = branch_ref.name().map_err(ArakiError::git(
+        "Could not convert branch reference into name.",
+    ))?;
+    repo.set_head(branch_ref_name)
+        .map_err(ArakiError::git("Unable to set the repository head"))?;
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use git2::{ObjectType, Repository};
+use glob::Pattern;
+use jiff::Timestamp;
+use log::warn;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::env::temp_dir;
+use std::fmt::Display;
+use std::fs;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::Workspace;
+use super::revision::commit_time;
+use crate::common;
+use crate::diff;
+use crate::error::ArakiError;
+use crate::lockspecs::{FORMATS, Package};
+use crate::remote::RemoteRepo;
+
+/// A tag of a workspace.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagInfo {
+    pub name: String,
+    /// Message of an annotated tag; lightweight tags have none
+    pub message: Option<String>,
+    /// Id of the tagged commit
+    pub commit: String,
+    /// Time the tag was created, or the commit was made for lightweight tags
+    pub date: Timestamp,
+    /// Name of the author of the tagged commit
+    pub author: String,
+    /// Number of distinct packages pinned in the tagged lockfile, if it could be parsed
+    pub packages: Option<usize>,
+    /// Whether the tag exists on the `origin` remote, if the remote could be reached
+    pub on_remote: Option<bool>,
+    /// Whether the tagged commit is checked out
+    pub current: bool,
+}
+
+/// How to order the tags returned by `list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagSort {
+    /// Alphabetically
+    #[default]
+    Name,
+    /// Newest first
+    Date,
+    /// Highest version first, e.g. `v1.10.0` before `v1.9.0`
+    Semver,
+}
+
+impl FromStr for TagSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(TagSort::Name),
+            "date" => Ok(TagSort::Date),
+            "semver" => Ok(TagSort::Semver),
+            _ => Err(format!("'{s}' is not one of 'name', 'date' or 'semver'")),
+        }
+    }
+}
+
+impl Display for TagSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TagSort::Name => "name",
+            TagSort::Date => "date",
+            TagSort::Semver => "semver",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Order of the tags
+    pub sort: TagSort,
+    /// Only list the tags whose name matches this glob pattern, e.g. `v1.*`
+    pub filter: Option<String>,
+    /// List at most this many tags, after sorting
+    pub limit: Option<usize>,
+}
+
+impl Workspace {
+    /// List the tags of the workspace.
+    ///
+    /// Whether each tag has been pushed is checked against the `origin` remote; if it can't be
+    /// reached, this is left unknown.
+    ///
+    /// * `options`: Options controlling which tags are listed and in which order
+    pub fn list(&self, options: &ListOptions) -> Result<Vec<TagInfo>, ArakiError> {
+        let mut tags = describe_tags(&self.repo, options)?;
+        if tags.is_empty() {
+            return Ok(tags);
+        }
+
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .ok()
+            .map(|commit| commit.id().to_string());
+        let remote_tags = self.remote_tags();
+        for tag in &mut tags {
+            let refname = format!("refs/tags/{}", tag.name);
+            tag.packages = count_packages(&tag.name, self.packages_at(&refname));
+            tag.on_remote = remote_tags.as_ref().map(|remote| remote.contains(&refname));
+            tag.current = head.as_ref() == Some(&tag.commit);
+        }
+        Ok(tags)
+    }
+
+    /// List the tags of a remote lockspec repository without cloning it. Only the tags and
+    /// the tagged commits are fetched, into a temporary repository which is deleted
+    /// afterwards.
+    ///
+    /// * `remote`: Lockspec repository
+    /// * `options`: Options controlling which tags are listed and in which order
+    pub fn list_remote(
+        remote: &RemoteRepo,
+        options: &ListOptions,
+    ) -> Result<Vec<TagInfo>, ArakiError> {
+        let temp_dir = temp_dir().join(format!("araki-list-{}", Uuid::new_v4()));
+        let result = common::fetch_tags(&remote.as_ssh_url(), &temp_dir)
+            .map_err(ArakiError::git(format!(
+                "Unable to list the tags of {remote}"
+            )))
+            .and_then(|repo| {
+                let mut tags = describe_tags(&repo, options)?;
+                for tag in &mut tags {
+                    let refname = format!("refs/tags/{}", tag.name);
+                    tag.packages = count_packages(&tag.name, packages_at(&repo, &refname));
+                    tag.on_remote = Some(true);
+                }
+                Ok(tags)
+            });
+        // Ignore any problems that arise during cleanup; just do our best
+        let _ = fs::remove_dir_all(&temp_dir);
+        result
+    }
+
+    /// List the tag references on the `origin` remote. A workspace without a remote has no
+    /// remote tags; `None` means the remote couldn't be reached.
+    fn remote_tags(&self) -> Option<HashSet<String>> {
+        if self.repo.find_remote("origin").is_err() {
+            return Some(HashSet::new());
+        }
+        match common::list_remote_refs(&self.repo, "origin") {
+            Ok(refs) => Some(
+                refs.into_iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| name.starts_with("refs/tags/"))
+                    .collect(),
+            ),
+            Err(err) => {
+                warn!("Unable to check which tags have been pushed: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// Describe the tags of a repository, filtered, sorted and limited according to `options`.
+/// The package count, remote status and checkout marker are left for the caller to fill in.
+///
+/// * `repo`: Repository containing the tags
+/// * `options`: Options controlling which tags are listed and in which order
+fn describe_tags(repo: &Repository, options: &ListOptions) -> Result<Vec<TagInfo>, ArakiError> {
+    let filter = options
+        .filter
+        .as_deref()
+        .map(Pattern::new)
+        .transpose()
+        .map_err(|err| ArakiError::Other(format!("Invalid filter: {err}")))?;
+    let references = repo
+        .references_glob("refs/tags/*")
+        .map_err(ArakiError::git("Unable to list tags"))?;
+
+    let mut tags = vec![];
+    for reference in references {
+        let reference = reference.map_err(ArakiError::git("Unable to list tags"))?;
+        // Tag names aren't required to be valid UTF-8, so show them lossily rather than skipping
+        // them
+        let name = String::from_utf8_lossy(
+            reference
+                .name_bytes()
+                .strip_prefix(b"refs/tags/")
+                .unwrap_or(reference.name_bytes()),
+        )
+        .into_owned();
+        if filter.as_ref().is_some_and(|filter| !filter.matches(&name)) {
+            continue;
+        }
+        let obj = reference
+            .peel(ObjectType::Tag)
+            .or_else(|_| reference.peel(ObjectType::Commit))
+            .map_err(ArakiError::git(format!("Unable to get tag {name}")))?;
+        let commit = obj.peel_to_commit().map_err(ArakiError::git(format!(
+            "{name} does not point to a commit"
+        )))?;
+        let tag = obj.as_tag();
+        tags.push(TagInfo {
+            name,
+            message: tag
+                .and_then(|tag| tag.message().ok().flatten())
+                .map(str::to_string),
+            commit: commit.id().to_string(),
+            date: tag
+                .and_then(|tag| tag.tagger())
+                .and_then(|tagger| Timestamp::from_second(tagger.when().seconds()).ok())
+                .unwrap_or_else(|| commit_time(&commit)),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            packages: None,
+            on_remote: None,
+            current: false,
+        });
+    }
+
+    match options.sort {
+        TagSort::Name => tags.sort_by(|a, b| a.name.cmp(&b.name)),
+        TagSort::Date => tags.sort_by(|a, b| b.date.cmp(&a.date).then(a.name.cmp(&b.name))),
+        TagSort::Semver => tags.sort_by(|a, b| diff::compare_versions(&b.name, &a.name)),
+    }
+    if let Some(limit) = options.limit {
+        tags.truncate(limit);
+    }
+    Ok(tags)
+}
+
+/// Parse the packages pinned at a revision of a repository whose lockspec format isn't known,
+/// by looking for the lockfile of each supported format.
+///
+/// * `repo`: Repository containing the lockspec
+/// * `revision`: Revision to read the lockfile at
+fn packages_at(repo: &Repository, revision: &str) -> Result<Vec<Package>, ArakiError> {
+    for format in FORMATS {
+        if let Ok(contents) = common::read_file_at_revision(repo, revision, format.lockfile_name())
+        {
+            return format
+                .parse_packages(&contents)
+                .map_err(ArakiError::LockSpec);
+        }
+    }
+    Err(ArakiError::LockSpec(format!(
+        "No supported lockfile found at {revision}"
+    )))
+}
+
+/// Count the distinct packages pinned in a tagged lockfile, warning if it couldn't be parsed.
+///
+/// * `tag`: Name of the tag
+/// * `packages`: Packages pinned in the lockfile of the tag
+fn count_packages(tag: &str, packages: Result<Vec<Package>, ArakiError>) -> Option<usize> {
+    match packages {
+        Ok(packages) => Some(
+            packages
+                .iter()
+                .map(|package| &package.name)
+                .collect::<BTreeSet<_>>()
+                .len(),
+        ),
+        Err(err) => {
+            warn!(tag = tag; "Unable to count the packages: {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use git2::build::CheckoutBuilder;
+use git2::{AnnotatedCommit, AutotagOption, FetchOptions, Oid, Reference, Repository, Signature};
+use log::info;
+
+use super::Workspace;
+use crate::common;
+use crate::error::ArakiError;
+use crate::identity;
+
+/// What pulling from the remote did to the local main branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullOutcome {
+    /// The local branch already contained the remote changes
+    UpToDate,
+    /// The local branch was fast-forwarded to the given commit
+    FastForward(Oid),
+    /// The remote changes were merged into the given merge commit
+    Merged(Oid),
+    /// The remote changes conflict with the local ones; the conflicts were written to the
+    /// working directory
+    Conflicts,
+}
+
+impl Workspace {
+    /// Fetch the main branch and tags from the remote, and merge the remote main branch into
+    /// the local one.
+    pub fn pull(&self) -> Result<PullOutcome, ArakiError> {
+        let repo = &self.repo;
+        let mut remote = repo
+            .find_remote("origin")
+            .map_err(ArakiError::git("Unable to find the origin remote"))?;
+
+        let mut fetch_opts = FetchOptions::new();
+        fetch_opts.remote_callbacks(common::generate_remote_callbacks());
+        fetch_opts.download_tags(AutotagOption::All);
+
+        // Pull changes
+        remote
+            .fetch(&["main"], Some(&mut fetch_opts), None)
+            .map_err(ArakiError::git("Unable to pull from remote"))?;
+
+        let fetch_commit = repo
+            .find_reference("FETCH_HEAD")
+            .and_then(|fetch_head| repo.reference_to_annotated_commit(&fetch_head))
+            .map_err(ArakiError::git("Unable to find the fetched commit"))?;
+
+        info!(commit:% = fetch_commit.id(); "Fetched the remote main branch");
+
+        // ref: https://github.com/rust-lang/git2-rs/blob/master/examples/pull.rs
+        // Determine type of merge
+        let (analysis, _) = repo
+            .merge_analysis(&[&fetch_commit])
+            .map_err(ArakiError::git("Unable to analyze the fetched changes"))?;
+        // Do the appropriate merge
+        if analysis.is_fast_forward() {
+            let refname = "refs/heads/main";
+            match repo.find_reference(refname) {
+                Ok(mut r) => fast_forward(repo, &mut r, &fetch_commit)
+                    .map_err(ArakiError::git("Unable to fast forward"))?,
+                Err(_) => {
+                    // The branch doesn't exist so just set the reference to the
+                    // commit directly. Usually this is because you are pulling
+                    // into an empty repository.
+                    repo.reference(
+                        refname,
+                        fetch_commit.id(),
+                        true,
+                        &format!("Setting main to {}", fetch_commit.id()),
+                    )
+                    .and_then(|_| repo.set_head(refname))
+                    .and_then(|_| {
+                        repo.checkout_head(Some(
+                            CheckoutBuilder::default()
+                                .allow_conflicts(true)
+                                .conflict_style_merge(true)
+                                .force(),
+                        ))
+                    })
+                    .map_err(ArakiError::git("Unable to checkout head"))?;
+                }
+            };
+            Ok(PullOutcome::FastForward(fetch_commit.id()))
+        } else if analysis.is_normal() {
+            let head_commit = repo
+                .head()
+                .and_then(|head| repo.reference_to_annotated_commit(&head))
+                .map_err(ArakiError::git("Unable to find HEAD"))?;
+            let config = self.lockspec.config().map_err(ArakiError::LockSpec)?;
+            let signature = identity::resolve_local_author(Some(repo), &config)?
+                .ok_or(ArakiError::Other(
+                    "Unable to determine the author of the merge commit: set `author` in the \
+                        araki config or `user.name` and `user.email` in git config"
+                        .to_string(),
+                ))?
+                .signature()?;
+            normal_merge(repo, &head_commit, &fetch_commit, &signature)
+                .map_err(ArakiError::git("Unable to merge"))
+        } else {
+            Ok(PullOutcome::UpToDate)
+        }
+    }
+}
+
+fn fast_forward(
+    repo: &Repository,
+    lb: &mut Reference,
+    rc: &AnnotatedCommit,
+) -> Result<(), git2::Error> {
+    let name = match lb.name() {
+        Ok(s) => s.to_string(),
+        Err(_) => String::from_utf8_lossy(lb.name_bytes()).to_string(),
+    };
+    let msg = format!("Fast-Forward: Setting {} to id: {}", name, rc.id());
+    lb.set_target(rc.id(), &msg)?;
+    repo.set_head(&name)?;
+    repo.checkout_head(Some(
+        CheckoutBuilder::default()
+            // For some reason the force is required to make the working directory actually get updated
+            // I suspect we should be adding some logic to handle dirty working directory states
+            // but this is just an example so maybe not.
+            .force(),
+    ))?;
+    Ok(())
+}
+
+fn normal_merge(
+    repo: &Repository,
+    local: &AnnotatedCommit,
+    remote: &AnnotatedCommit,
+    sig: &Signature,
+) -> Result<PullOutcome, git2::Error> {
+    let local_tree = repo.find_commit(local.id())?.tree()?;
+    let remote_tree = repo.find_commit(remote.id())?.tree()?;
+    let ancestor = repo
+        .find_commit(repo.merge_base(local.id(), remote.id())?)?
+        .tree()?;
+    let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
+
+    if idx.has_conflicts() {
+        repo.checkout_index(Some(&mut idx), None)?;
+        return Ok(PullOutcome::Conflicts);
+    }
+    let result_tree = repo.find_tree(idx.write_tree_to(repo)?)?;
+    // now create the merge commit
+    let msg = format!("Merge: {} into {}", remote.id(), local.id());
+    let local_commit = repo.find_commit(local.id())?;
+    let remote_commit = repo.find_commit(remote.id())?;
+    // Do our merge commit and set current branch head to that commit.
+    let merge_commit = repo.commit(
+        Some("HEAD"),
+        sig,
+        sig,
+        &msg,
+        &result_tree,
+        &[&local_commit, &remote_commit],
+    )?;
+    // Set working tree to match head.
+    repo.checkout_head(None)?;
+    Ok(PullOutcome::Merged(merge_commit))
+}
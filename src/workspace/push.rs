@@ -0,0 +1,53 @@
+use super::Workspace;
+use crate::common;
+use crate::error::ArakiError;
+use crate::lockspecs;
+
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    /// Skip checking that the tagged lockfile is consistent with the tagged specfile
+    pub no_verify: bool,
+}
+
+impl Workspace {
+    /// Push the checked out branch and a tag to the remote lockspec repository. Only the tag is
+    /// pushed when HEAD is detached.
+    ///
+    /// * `tag`: Name of the tag to push
+    /// * `options`: Options controlling how the tag is pushed
+    pub fn push(&self, tag: &str, options: &PushOptions) -> Result<(), ArakiError> {
+        let tag_ref = format!("refs/tags/{tag}");
+
+        if !options.no_verify {
+            // Check what is actually being pushed, rather than the working files
+            let format = self.lockspec.format;
+            let read = |name: &str| {
+                common::read_file_at_revision(&self.repo, &tag_ref, name).map_err(ArakiError::git(
+                    format!("Unable to read {name} at tag {tag}"),
+                ))
+            };
+            let problems = lockspecs::check_contents(
+                format,
+                &read(format.specfile_name())?,
+                &read(format.lockfile_name())?,
+            );
+            if !problems.is_empty() {
+                return Err(ArakiError::Inconsistent(problems));
+            }
+        }
+
+        let head = self
+            .repo
+            .head()
+            .map_err(ArakiError::git("Unable to read HEAD"))?;
+        let mut refspecs = vec![];
+        if head.is_branch() {
+            refspecs.push(head.name().map_err(ArakiError::git(
+                "Could not convert branch reference into name.",
+            ))?);
+        }
+        refspecs.push(tag_ref.as_str());
+        common::git_push(&self.repo, "origin", &refspecs)
+            .map_err(ArakiError::git("Unable to push to remote"))
+    }
+}
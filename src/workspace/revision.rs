@@ -0,0 +1,245 @@
+use git2::{Commit, Oid, Sort};
+use jiff::civil::{Date, DateTime, Time};
+use jiff::tz::TimeZone;
+use jiff::{SignedDuration, Timestamp, Zoned};
+use log::debug;
+use std::fmt::Display;
+
+use super::Workspace;
+use crate::error::ArakiError;
+
+/// Branch checked out by `latest`.
+const MAIN_BRANCH: &str = "refs/heads/main";
+
+/// What a revision passed to `checkout` refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevisionKind {
+    /// A tag, which is checked out on a detached HEAD
+    Tag(String),
+    /// A branch, which stays checked out so that new commits go onto it
+    Branch(String),
+    /// Any other commit, e.g. `latest~2` or an abbreviated commit id
+    Commit,
+}
+
+/// A revision resolved to a commit of the lockspec repository.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub kind: RevisionKind,
+    pub commit: Oid,
+    /// Time the commit was made
+    pub time: Timestamp,
+}
+
+impl Display for Revision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let commit = &self.commit.to_string()[..7];
+        let date = self.time.to_zoned(TimeZone::system()).date();
+        match &self.kind {
+            RevisionKind::Tag(name) => write!(f, "tag {name} ({commit}, {date})"),
+            RevisionKind::Branch(name) => write!(f, "branch {name} ({commit}, {date})"),
+            RevisionKind::Commit => write!(f, "commit {commit} ({date})"),
+        }
+    }
+}
+
+impl Workspace {
+    /// Resolve a revision to a commit.
+    ///
+    /// Besides the names of tags and branches, a revision can be:
+    /// * `latest`, for the main branch, optionally followed by `~N`, `^` or `@{N}` like any other
+    ///   name
+    /// * a full or abbreviated commit id, or anything else git understands
+    /// * `previous`, for the tag checked out before the current one
+    /// * a revision followed by `@{DATE}`, for the last commit of its history made on or before
+    ///   a date such as `2025-06-01` or `2025-06-01T12:00`. The revision defaults to `latest`
+    ///
+    /// Tags take precedence over branches of the same name.
+    ///
+    /// * `revision`: Revision to resolve
+    pub fn resolve(&self, revision: &str) -> Result<Revision, ArakiError> {
+        let not_found = || ArakiError::RevisionNotFound(revision.to_string());
+
+        if revision == "previous" {
+            let name = self.previous_tag()?.ok_or_else(not_found)?;
+            return self.resolve(&name);
+        }
+
+        if let Some((base, date)) = split_date(revision) {
+            let until = parse_date(date)?;
+            let base = self.resolve(if base.is_empty() { "latest" } else { base })?;
+            let commit = self.last_commit_until(base.commit, until)?;
+            let commit = commit.ok_or_else(not_found)?;
+            return Ok(Revision {
+                kind: RevisionKind::Commit,
+                commit: commit.id(),
+                time: commit_time(&commit),
+            });
+        }
+
+        let (kind, spec) = if revision == "latest" {
+            (
+                RevisionKind::Branch("main".to_string()),
+                MAIN_BRANCH.to_string(),
+            )
+        } else if let Some(suffix) = revision.strip_prefix("latest")
+            && suffix.starts_with(['~', '^', '@'])
+        {
+            (RevisionKind::Commit, format!("{MAIN_BRANCH}{suffix}"))
+        } else if self
+            .repo
+            .find_reference(&format!("refs/tags/{revision}"))
+            .is_ok()
+        {
+            (
+                RevisionKind::Tag(revision.to_string()),
+                format!("refs/tags/{revision}"),
+            )
+        } else if self
+            .repo
+            .find_reference(&format!("refs/heads/{revision}"))
+            .is_ok()
+        {
+            (
+                RevisionKind::Branch(revision.to_string()),
+                format!("refs/heads/{revision}"),
+            )
+        } else {
+            (RevisionKind::Commit, revision.to_string())
+        };
+        let commit = self
+            .repo
+            .revparse_single(&spec)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| not_found())?;
+        debug!(revision = revision, spec = spec, commit:% = commit.id(); "Resolved revision");
+        Ok(Revision {
+            kind,
+            commit: commit.id(),
+            time: commit_time(&commit),
+        })
+    }
+
+    /// Find the tag checked out before the current commit, from the HEAD reflog.
+    fn previous_tag(&self) -> Result<Option<String>, ArakiError> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(ArakiError::git("Unable to find the HEAD commit"))?;
+        let tags_by_commit = self.tags_by_commit()?;
+        let reflog = self
+            .repo
+            .reflog("HEAD")
+            .map_err(ArakiError::git("Unable to read the HEAD reflog"))?;
+        Ok(reflog
+            .iter()
+            .map(|entry| entry.id_new())
+            .filter(|commit| *commit != head.id())
+            .find_map(|commit| tags_by_commit.get(&commit).cloned()))
+    }
+
+    /// Find the last commit made until a time in the first-parent history of a commit.
+    ///
+    /// * `start`: Commit whose history is searched
+    /// * `until`: Latest commit time accepted
+    fn last_commit_until(
+        &self,
+        start: Oid,
+        until: Timestamp,
+    ) -> Result<Option<Commit<'_>>, ArakiError> {
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(ArakiError::git("Unable to walk the history"))?;
+        revwalk
+            .push(start)
+            .and_then(|()| revwalk.simplify_first_parent())
+            .and_then(|()| revwalk.set_sorting(Sort::TIME))
+            .map_err(ArakiError::git("Unable to walk the history"))?;
+        for oid in revwalk {
+            let commit = oid
+                .and_then(|oid| self.repo.find_commit(oid))
+                .map_err(ArakiError::git("Unable to walk the history"))?;
+            if commit_time(&commit) <= until {
+                return Ok(Some(commit));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Time a commit was made.
+pub(super) fn commit_time(commit: &Commit) -> Timestamp {
+    Timestamp::from_second(commit.time().seconds()).unwrap_or_default()
+}
+
+/// Split a revision like `v1@{2025-06-01}` into the revision and the date. Reflog selectors
+/// such as `@{1}` or `@{-1}` are left to git.
+///
+/// * `revision`: Revision to split
+fn split_date(revision: &str) -> Option<(&str, &str)> {
+    let (base, date) = revision.strip_suffix('}')?.rsplit_once("@{")?;
+    if date.parse::<i64>().is_ok() {
+        return None;
+    }
+    Some((base, date))
+}
+
+/// Parse the date of a `@{DATE}` revision or `--as-of` into the latest time it covers: a date
+/// covers the whole day, in the local time zone.
+///
+/// * `date`: A date (`2025-06-01`), a local time (`2025-06-01T12:00`) or a timestamp
+///   (`2025-06-01T12:00Z`)
+fn parse_date(date: &str) -> Result<Timestamp, ArakiError> {
+    let local = |datetime: DateTime| datetime.to_zoned(TimeZone::system());
+    // A date also parses from a time, so try it only when there is no time
+    let has_time = date.contains(['T', 't', ' ']);
+    let until = if let Ok(timestamp) = date.parse::<Timestamp>() {
+        Ok(timestamp)
+    } else if let Ok(day) = date.parse::<Date>()
+        && !has_time
+    {
+        day.tomorrow()
+            .and_then(|tomorrow| local(tomorrow.to_datetime(Time::midnight())))
+            .map(|midnight| midnight.timestamp() - SignedDuration::from_secs(1))
+    } else if let Ok(datetime) = date.parse::<DateTime>() {
+        local(datetime).map(|zoned: Zoned| zoned.timestamp())
+    } else {
+        return Err(ArakiError::Other(format!(
+            "'{date}' is not a date such as 2025-06-01 or 2025-06-01T12:00"
+        )));
+    };
+    until.map_err(|err| ArakiError::Other(format!("'{date}' is out of range: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_date() {
+        assert_eq!(split_date("@{2025-06-01}"), Some(("", "2025-06-01")));
+        assert_eq!(split_date("v1@{2025-06-01}"), Some(("v1", "2025-06-01")));
+        assert_eq!(split_date("main@{1}"), None);
+        assert_eq!(split_date("@{-1}"), None);
+        assert_eq!(split_date("v1"), None);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let utc = parse_date("2025-06-01T12:00Z").unwrap();
+        assert_eq!(utc, "2025-06-01T12:00:00Z".parse::<Timestamp>().unwrap());
+
+        // A day lasts until its last second in the local time zone
+        let day = parse_date("2025-06-01").unwrap();
+        let local = day.to_zoned(TimeZone::system());
+        assert_eq!(local.date(), Date::constant(2025, 6, 1));
+        assert_eq!((local.hour(), local.minute(), local.second()), (23, 59, 59));
+
+        let noon = parse_date("2025-06-01T12:00").unwrap();
+        assert_eq!(noon.to_zoned(TimeZone::system()).hour(), 12);
+
+        assert!(parse_date("last tuesday").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Signature};
+use log::info;
+
+use super::Workspace;
+use crate::common;
+use crate::error::ArakiError;
+use crate::lockspecs::LockSpec;
+
+/// Reference holding the stashed lockspec. There is a single slot, so a stash has to be popped
+/// before another one can be saved.
+const STASH_REF: &str = "refs/araki/stash";
+
+impl Workspace {
+    /// Save the uncommitted changes to the lockspec in a temporary commit, so that they can be
+    /// restored with `stash_pop` after checking out another tag. Returns the id of the commit.
+    ///
+    /// The working directory is left untouched.
+    pub fn stash(&self) -> Result<Oid, ArakiError> {
+        if self.repo.find_reference(STASH_REF).is_ok() {
+            return Err(ArakiError::StashExists);
+        }
+        let commit = self.snapshot()?;
+        self.repo
+            .reference(STASH_REF, commit, false, "araki stash")
+            .map_err(ArakiError::git("Unable to save the stash"))?;
+        info!(commit:% = commit; "Stashed the lockspec changes");
+        Ok(commit)
+    }
+
+    /// Save the current lockspec files in a commit on top of HEAD, without any reference
+    /// pointing to it. The working directory is left untouched.
+    pub(super) fn snapshot(&self) -> Result<Oid, ArakiError> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(ArakiError::git("Unable to find the HEAD commit"))?;
+
+        let mut index =
+            common::stage_lockspec(&self.repo, &self.lockspec).map_err(ArakiError::LockSpec)?;
+        let tree_oid = index.write_tree().map_err(ArakiError::git(
+            "Failed to write the git tree from the index",
+        ))?;
+        let tree = self
+            .repo
+            .find_tree(tree_oid)
+            .map_err(ArakiError::git("Unable to find the stashed git tree"))?;
+
+        // The stash never leaves the machine, so it doesn't need a real identity
+        let signature = self
+            .repo
+            .signature()
+            .or_else(|_| Signature::now("araki", "araki@localhost"))
+            .map_err(ArakiError::git(
+                "Unable to create a signature for the stash",
+            ))?;
+        self.repo
+            .commit(None, &signature, &signature, "araki stash", &tree, &[&head])
+            .map_err(ArakiError::git("Unable to save the lockspec changes"))
+    }
+
+    /// Write the lockspec files saved by `snapshot` to the working directory, and re-detect
+    /// the lockspec. The index is kept at HEAD, so that the restored files show up as changes.
+    ///
+    /// * `commit`: Commit created by `snapshot`
+    pub(super) fn restore_snapshot(&mut self, commit: Oid) -> Result<(), ArakiError> {
+        let commit = self
+            .repo
+            .find_commit(commit)
+            .map_err(ArakiError::git("Unable to read the saved lockspec changes"))?;
+        self.repo
+            .checkout_tree(
+                commit.as_object(),
+                Some(CheckoutBuilder::new().force().update_index(false)),
+            )
+            .map_err(ArakiError::git(
+                "Unable to restore the saved lockspec changes",
+            ))?;
+        self.lockspec = LockSpec::from_path(self.path())
+            .map_err(|_| ArakiError::NoLockSpec(self.path().to_path_buf()))?;
+        Ok(())
+    }
+
+    /// Restore the changes saved with `stash` on top of the checked out tag, and drop the
+    /// stash.
+    ///
+    /// Refuses to overwrite uncommitted changes to the lockspec.
+    pub fn stash_pop(&mut self) -> Result<(), ArakiError> {
+        let commit = self
+            .repo
+            .find_reference(STASH_REF)
+            .map_err(|_| ArakiError::NoStash)?
+            .peel_to_commit()
+            .map_err(ArakiError::git("Unable to read the stash"))?
+            .id();
+        let changes = self.status()?;
+        if !changes.is_empty() {
+            return Err(ArakiError::DirtyWorkspace(changes));
+        }
+
+        self.restore_snapshot(commit)?;
+        self.drop_stash()?;
+        info!(commit:% = commit; "Restored the stashed lockspec changes");
+        Ok(())
+    }
+
+    /// Delete the stash without restoring it.
+    pub(super) fn drop_stash(&self) -> Result<(), ArakiError> {
+        self.repo
+            .find_reference(STASH_REF)
+            .and_then(|mut stash| stash.delete())
+            .map_err(ArakiError::git("Unable to drop the stash"))
+    }
+}
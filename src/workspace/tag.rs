@@ -0,0 +1,538 @@
+use git2::{BranchType, Object, ObjectType, Oid, Reference, ResetType, Signature};
+use jiff::Zoned;
+use log::{info, warn};
+use regex::Regex;
+
+use super::{Workspace, check_lockspec};
+use crate::common;
+use crate::config::{ArakiConfig, SigningConfig, UserConfig};
+use crate::diff::{self, PackageChange};
+use crate::error::ArakiError;
+use crate::identity::{self, Identity};
+use crate::signing;
+use crate::version::{self, TagBump};
+
+#[derive(Debug, Clone, Default)]
+pub struct TagOptions {
+    /// Description of the tag
+    pub description: Option<String>,
+    /// Skip checking that the lockfile is consistent with the specfile
+    pub no_verify: bool,
+    /// Author of the commit; resolved with `identity::resolve_author` if unset
+    pub author: Option<Identity>,
+    /// Committer of the commit and tagger of the tag; defaults to the author
+    pub committer: Option<Identity>,
+    /// Move the tag if it already exists, as long as it hasn't been pushed
+    pub force: bool,
+    /// Tag the HEAD commit if the lockspec hasn't changed since, instead of refusing
+    pub allow_empty: bool,
+    /// Sign the tag with the key configured in the user configuration; tags are also signed
+    /// if `sign_tags` is set there
+    pub sign: bool,
+    /// Branch to create for the new commit when HEAD is detached, e.g. after checking out a
+    /// tag; tagging a new commit on a detached HEAD is refused otherwise
+    pub branch: Option<String>,
+}
+
+/// Called with the last tag, if any, and the package changes since it, before anything is
+/// committed.
+pub type ChangesHook<'a> = &'a dyn Fn(Option<&str>, &[PackageChange]);
+
+/// Names which can't be used for tags, because they have a special meaning to araki.
+const RESERVED_TAG_NAMES: &[&str] = &["latest"];
+
+/// Check that a tag name is a valid git ref name, isn't reserved by araki, and matches the
+/// `tag_pattern` of the araki configuration if there is one.
+///
+/// * `name`: Name of the tag
+/// * `config`: araki configuration of the lockspec
+pub fn validate_tag_name(name: &str, config: &ArakiConfig) -> Result<(), ArakiError> {
+    let invalid = |reason: String| ArakiError::InvalidTagName {
+        name: name.to_string(),
+        reason,
+    };
+    if !Reference::is_valid_name(&format!("refs/tags/{name}")) {
+        return Err(invalid(
+            "tag names can't contain spaces, `..`, `~`, `^`, `:`, `?`, `*`, `[` or `\\`, or \
+                start or end with `/` or `.`"
+                .to_string(),
+        ));
+    }
+    if RESERVED_TAG_NAMES.contains(&name) {
+        return Err(invalid(format!("`{name}` is reserved by araki")));
+    }
+    if let Some(pattern) = &config.tag_pattern {
+        let regex = Regex::new(pattern).map_err(|err| {
+            ArakiError::LockSpec(format!("Invalid tag_pattern in the araki config: {err}"))
+        })?;
+        if !regex.is_match(name) {
+            return Err(invalid(format!(
+                "it doesn't match the tag_pattern `{pattern}` of the araki config"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Build the message of an annotated tag: the description, if any, followed by a summary of
+/// the package changes since the last tag.
+///
+/// * `name`: Name of the tag
+/// * `description`: Description of the tag given by the user
+/// * `last_tag`: Name of the last tag, if any
+/// * `changes`: Package changes since the last tag, if they could be computed
+fn tag_message(
+    name: &str,
+    description: Option<&str>,
+    last_tag: Option<&str>,
+    changes: Option<&[PackageChange]>,
+) -> String {
+    let summary = match (changes, last_tag) {
+        (None, _) => None,
+        (Some(changes), None) => Some(format!(
+            "First tag, with {}",
+            diff::locked_packages(changes)
+        )),
+        (Some([]), Some(last_tag)) => Some(format!("No package changes since {last_tag}")),
+        (Some(changes), Some(last_tag)) => Some(format!(
+            "{} since {last_tag}\n\n{}",
+            diff::headline(changes),
+            diff::format_changes(changes).trim_end()
+        )),
+    };
+    match (description, summary) {
+        (Some(description), Some(summary)) => format!("{}\n\n{summary}", description.trim_end()),
+        (Some(description), None) => description.to_string(),
+        (None, Some(summary)) => summary,
+        (None, None) => format!("araki environment tag: {name}"),
+    }
+}
+
+impl Workspace {
+    /// Commit the current state of the lockspec and tag it, returning the id of the tag.
+    ///
+    /// * `name`: Name of the tag
+    /// * `options`: Options controlling how the tag is created
+    /// * `on_changes`: Called with the package changes since the last tag before committing
+    pub async fn tag(
+        &self,
+        name: &str,
+        options: &TagOptions,
+        on_changes: ChangesHook<'_>,
+    ) -> Result<Oid, ArakiError> {
+        let repo = &self.repo;
+        let config = self.lockspec.config().map_err(ArakiError::LockSpec)?;
+
+        // Make sure the tag can be created before committing anything
+        validate_tag_name(name, &config)?;
+        if repo.find_reference(&format!("refs/tags/{name}")).is_ok() {
+            if !options.force {
+                return Err(ArakiError::TagExists(name.to_string()));
+            }
+            if self.is_tag_on_remote(name)? {
+                return Err(ArakiError::TagOnRemote(name.to_string()));
+            }
+            info!(tag = name; "Moving unpushed tag");
+        }
+        let signing = UserConfig::load()?.signing;
+        let sign = options.sign || signing.sign_tags;
+        if sign {
+            signing::signing_key(&signing)?;
+        }
+        if !options.no_verify {
+            check_lockspec(&self.lockspec)?;
+        }
+        let unchanged = self.status()?.is_empty();
+        if unchanged && !options.allow_empty {
+            return Err(ArakiError::NothingToTag);
+        }
+        let detached = repo
+            .head_detached()
+            .map_err(ArakiError::git("Unable to read HEAD"))?;
+        if detached && !unchanged && options.branch.is_none() {
+            return Err(ArakiError::DetachedHead);
+        }
+
+        let last_tag = self.last_tag()?;
+        let since = last_tag.as_ref().map(|tag| format!("refs/tags/{tag}"));
+        let changes = match self.package_changes(since.as_deref()) {
+            Ok(changes) => {
+                on_changes(last_tag.as_deref(), &changes);
+                Some(changes)
+            }
+            // The lockfile may not parse when the check is skipped
+            Err(err) => {
+                warn!("Unable to compare the packages with the last tag: {err}");
+                None
+            }
+        };
+
+        let author = identity::resolve_author(Some(repo), options.author.as_ref(), &config).await?;
+        let committer = identity::resolve_committer(options.committer.as_ref(), &config, &author)?;
+
+        let committer_signature = committer.signature()?;
+        let head = repo
+            .head()
+            .and_then(|head| head.peel(ObjectType::Commit))
+            .map_err(ArakiError::git("Failed to find the HEAD commit"))?;
+        let tag_message = tag_message(
+            name,
+            options.description.as_deref(),
+            last_tag.as_deref(),
+            changes.as_deref(),
+        );
+        if unchanged {
+            info!("Tagging the HEAD commit, since the lockspec hasn't changed");
+            return self.create_tag(
+                name,
+                &head,
+                &committer_signature,
+                &tag_message,
+                options.force,
+                sign.then_some(&signing),
+            );
+        }
+
+        let branch = options.branch.as_deref().filter(|_| detached);
+        if let Some(branch) = branch {
+            self.create_branch(branch)?;
+        }
+        let result = self
+            .commit_lockspec(name, &author, &committer)
+            .and_then(|target| {
+                self.create_tag(
+                    name,
+                    &target,
+                    &committer_signature,
+                    &tag_message,
+                    options.force,
+                    sign.then_some(&signing),
+                )
+            });
+        // Without the tag, the commit would make the lockspec look tagged already
+        result.map_err(|error| match self.undo_commit(head.id(), branch) {
+            Ok(()) => error,
+            Err(rollback) => ArakiError::Rollback {
+                error: Box::new(error),
+                rollback: rollback.to_string(),
+            },
+        })
+    }
+
+    /// Create an annotated tag, signed if a signing configuration is given.
+    ///
+    /// * `name`: Name of the tag
+    /// * `target`: Commit to tag
+    /// * `tagger`: Tagger of the tag
+    /// * `message`: Message of the tag
+    /// * `force`: Overwrite an existing tag of the same name
+    /// * `signing`: Signing configuration of the user, if the tag should be signed
+    fn create_tag(
+        &self,
+        name: &str,
+        target: &Object,
+        tagger: &Signature,
+        message: &str,
+        force: bool,
+        signing: Option<&SigningConfig>,
+    ) -> Result<Oid, ArakiError> {
+        if let Some(signing) = signing {
+            let tag_oid = signing::create_signed_tag(
+                &self.repo,
+                name,
+                target.id(),
+                tagger,
+                message,
+                force,
+                signing,
+            )?;
+            info!(tag = name, format:? = signing.format; "Signed tag");
+            return Ok(tag_oid);
+        }
+        self.repo
+            .tag(name, target, tagger, message, force)
+            .map_err(ArakiError::git("Unable to tag"))
+    }
+
+    /// Undo the commit made for a tag which couldn't be created, leaving the files as they are:
+    /// HEAD and its branch go back to the previous commit, and the branch created for the
+    /// commit, if any, is deleted.
+    ///
+    /// * `previous`: Commit HEAD was on before committing
+    /// * `created_branch`: Branch created for the commit on a detached HEAD
+    fn undo_commit(&self, previous: Oid, created_branch: Option<&str>) -> Result<(), ArakiError> {
+        let previous_commit = self
+            .repo
+            .find_object(previous, Some(ObjectType::Commit))
+            .map_err(ArakiError::git("Unable to find the previous commit"))?;
+        self.repo
+            .reset(&previous_commit, ResetType::Mixed, None)
+            .map_err(ArakiError::git("Unable to reset HEAD"))?;
+        if let Some(name) = created_branch {
+            self.repo
+                .set_head_detached(previous)
+                .map_err(ArakiError::git("Unable to detach HEAD"))?;
+            self.repo
+                .find_branch(name, BranchType::Local)
+                .and_then(|mut branch| branch.delete())
+                .map_err(ArakiError::git(format!("Unable to delete branch '{name}'")))?;
+        }
+        warn!(commit:% = previous; "Restored HEAD, since the tag couldn't be created");
+        Ok(())
+    }
+
+    /// Commit the changes to the files tracked for the lockspec to HEAD.
+    ///
+    /// * `message`: Commit message
+    /// * `author`: Author of the commit
+    /// * `committer`: Committer of the commit
+    fn commit_lockspec(
+        &self,
+        message: &str,
+        author: &Identity,
+        committer: &Identity,
+    ) -> Result<Object<'_>, ArakiError> {
+        let repo = &self.repo;
+        // Add files
+        let mut index = common::stage_lockspec(repo, &self.lockspec)?;
+
+        let tree_oid = index
+            .write_tree()
+            .map_err(ArakiError::git("Failed to write tree"))?;
+        let tree = repo
+            .find_tree(tree_oid)
+            .map_err(ArakiError::git("Failed to find tree"))?;
+        let author_signature = author.signature()?;
+        let committer_signature = committer.signature()?;
+        let parent_commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(ArakiError::git("Failed to find the parent commit"))?;
+
+        // Commit change
+        let commit_oid = repo
+            .commit(
+                Some("HEAD"),         // Update the HEAD reference
+                &author_signature,    // Author
+                &committer_signature, // Committer
+                message,              // Commit message
+                &tree,                // Tree containing the staged changes
+                &[&parent_commit],    // Parent commit(s)
+            )
+            .map_err(ArakiError::git("Failed to create commit"))?;
+
+        info!(commit:% = commit_oid, author:% = author, committer:% = committer; "Committed lockspec changes");
+
+        repo.find_object(commit_oid, None)
+            .map_err(ArakiError::git("Unable to find the new commit"))
+    }
+
+    /// Create a branch at the HEAD commit and attach HEAD to it.
+    ///
+    /// * `name`: Name of the branch
+    fn create_branch(&self, name: &str) -> Result<(), ArakiError> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(ArakiError::git("Unable to find the HEAD commit"))?;
+        let branch = self
+            .repo
+            .branch(name, &head, false)
+            .map_err(ArakiError::git(format!("Unable to create branch '{name}'")))?;
+        let refname = branch.into_reference();
+        let refname = refname.name().map_err(ArakiError::git(
+            "Could not convert branch reference into name.",
+        ))?;
+        self.repo
+            .set_head(refname)
+            .map_err(ArakiError::git("Unable to set the repository head"))?;
+        info!(branch = name; "Created branch");
+        Ok(())
+    }
+
+    /// Delete a tag. The tag can't be deleted while it is checked out.
+    ///
+    /// * `name`: Name of the tag
+    /// * `remote`: Also delete the tag from the `origin` remote
+    pub fn delete_tag(&self, name: &str, remote: bool) -> Result<(), ArakiError> {
+        let refname = format!("refs/tags/{name}");
+        let local = self.repo.find_reference(&refname).ok();
+        let on_remote = remote && self.is_tag_on_remote(name)?;
+        if local.is_none() && !on_remote {
+            return Err(ArakiError::RevisionNotFound(name.to_string()));
+        }
+
+        if let Some(tag_ref) = &local
+            && self.repo.head_detached().unwrap_or(false)
+        {
+            let tag_commit = tag_ref.peel_to_commit().map_err(ArakiError::git(format!(
+                "{name} does not point to a commit"
+            )))?;
+            let head_commit = self
+                .repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .map_err(ArakiError::git("Unable to find HEAD"))?;
+            if tag_commit.id() == head_commit.id() {
+                return Err(ArakiError::TagCheckedOut(name.to_string()));
+            }
+        }
+
+        // Delete the remote tag first, so that a failure leaves everything as it was
+        if on_remote {
+            common::git_push(&self.repo, "origin", &[&format!(":{refname}")]).map_err(
+                ArakiError::git(format!("Unable to delete tag '{name}' from the remote")),
+            )?;
+            info!(tag = name; "Deleted tag from the remote");
+        } else if remote {
+            warn!(tag = name; "Tag isn't on the remote");
+        }
+        if let Some(mut tag_ref) = local {
+            tag_ref
+                .delete()
+                .map_err(ArakiError::git(format!("Unable to delete tag '{name}'")))?;
+            info!(tag = name; "Deleted tag");
+        }
+        Ok(())
+    }
+
+    /// Compute the name of the next tag from the existing tags of the workspace.
+    ///
+    /// * `bump`: How to compute the name
+    pub fn next_tag_name(&self, bump: TagBump) -> Result<String, ArakiError> {
+        let names = self
+            .repo
+            .tag_names(None)
+            .map_err(ArakiError::git("Unable to list tags"))?;
+        let name =
+            version::next_tag_name(bump, names.iter().flatten().flatten(), Zoned::now().date());
+        info!(bump:% = bump, tag = name; "Computed the next tag name");
+        Ok(name)
+    }
+
+    /// Check whether a tag exists on the `origin` remote. A workspace without a remote has
+    /// nothing pushed.
+    ///
+    /// * `name`: Name of the tag
+    pub fn is_tag_on_remote(&self, name: &str) -> Result<bool, ArakiError> {
+        if self.repo.find_remote("origin").is_err() {
+            return Ok(false);
+        }
+        let refname = format!("refs/tags/{name}");
+        let refs = common::list_remote_refs(&self.repo, "origin").map_err(ArakiError::git(
+            format!("Unable to check whether tag '{name}' has been pushed to the remote"),
+        ))?;
+        Ok(refs.iter().any(|(remote_ref, _)| *remote_ref == refname))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ChangeKind;
+    use crate::workspace::CheckoutOptions;
+    use crate::workspace::tests::{append, commit, workspace};
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_tag_on_detached_head() {
+        let mut workspace = workspace();
+        let v1 = commit(&workspace, "v1");
+        let target = workspace.repo.find_object(v1, None).unwrap();
+        workspace
+            .repo
+            .tag_lightweight("v1", &target, false)
+            .unwrap();
+        drop(target);
+        let no_install = CheckoutOptions {
+            no_install: true,
+            ..Default::default()
+        };
+        workspace.checkout("v1", &no_install).unwrap();
+        append(&workspace, "pixi.toml", "# v2");
+
+        // A new commit on a detached HEAD would be on no branch
+        let options = TagOptions {
+            no_verify: true,
+            author: Some(Identity::new("araki", "araki@example.com")),
+            ..Default::default()
+        };
+        let result = workspace.tag("v2", &options, &|_, _| {}).await;
+        assert!(matches!(result, Err(ArakiError::DetachedHead)));
+        assert_eq!(workspace.repo.head().unwrap().target(), Some(v1));
+
+        let options = TagOptions {
+            branch: Some("fix".to_string()),
+            ..options
+        };
+        workspace.tag("v2", &options, &|_, _| {}).await.unwrap();
+        let head = workspace.repo.head().unwrap();
+        assert_eq!(head.name(), Ok("refs/heads/fix"));
+        assert_eq!(head.peel_to_commit().unwrap().parent_id(0).unwrap(), v1);
+        assert!(workspace.status().unwrap().is_empty());
+        fs::remove_dir_all(workspace.path()).unwrap();
+    }
+
+    #[test]
+    fn test_validate_tag_name() {
+        let config = ArakiConfig::default();
+        assert!(validate_tag_name("v1.2.0", &config).is_ok());
+        assert!(validate_tag_name("release/2025-01", &config).is_ok());
+        for name in ["", "has space", "a..b", "v1~1", "ends.", "x.lock", "latest"] {
+            assert!(validate_tag_name(name, &config).is_err(), "{name}");
+        }
+
+        let config = ArakiConfig {
+            tag_pattern: Some(r"^v\d+$".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_tag_name("v2", &config).is_ok());
+        assert!(validate_tag_name("2", &config).is_err());
+    }
+
+    #[test]
+    fn test_tag_message() {
+        let changes = [PackageChange {
+            environment: "default".to_string(),
+            platform: "linux-64".to_string(),
+            name: "python".to_string(),
+            kind: ChangeKind::Upgraded,
+            old_version: Some("3.12.1".to_string()),
+            new_version: Some("3.13.0".to_string()),
+        }];
+        assert_eq!(
+            tag_message("v2", Some("New python"), Some("v1"), Some(&changes)),
+            "New python\n\n1 upgraded since v1\n\nlinux-64:\n  ↑ python 3.12.1 -> 3.13.0"
+        );
+        assert_eq!(
+            tag_message("v1", None, None, Some(&changes)),
+            "First tag, with 1 locked package"
+        );
+
+        // Packages locked for several environments and platforms are counted once
+        let mut locked = vec![];
+        for environment in ["default", "test"] {
+            for platform in ["linux-64", "osx-arm64", "win-64"] {
+                for name in ["python", "numpy"] {
+                    locked.push(PackageChange {
+                        environment: environment.to_string(),
+                        platform: platform.to_string(),
+                        name: name.to_string(),
+                        kind: ChangeKind::Added,
+                        old_version: None,
+                        new_version: Some("1.0".to_string()),
+                    });
+                }
+            }
+        }
+        assert_eq!(
+            tag_message("v1", None, None, Some(&locked)),
+            "First tag, with 2 locked packages"
+        );
+        assert_eq!(
+            tag_message("v2", Some("New python"), Some("v1"), None),
+            "New python"
+        );
+    }
+}